@@ -0,0 +1,124 @@
+use std::sync::{Arc, Mutex};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use rayon::prelude::*;
+
+const LINE_CNT: usize = 1_000_000;
+const WORDS: [&str; 12] = [
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa",
+    "lambda", "mu",
+];
+
+// a screenful of items
+const TOP_N: usize = 50;
+
+/// 1M lines like `gamma/zeta-kappa_42`, generated with a fixed seed
+fn synthetic_lines() -> Vec<String> {
+    let mut seed: u64 = 42;
+    let mut next = || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize
+    };
+    (0..LINE_CNT)
+        .map(|i| {
+            let mut line = String::new();
+            for sep in ["/", "-", "_"].iter().take(next() % 3 + 1) {
+                line.push_str(WORDS[next() % WORDS.len()]);
+                line.push_str(sep);
+            }
+            line.push_str(&i.to_string());
+            line
+        })
+        .collect()
+}
+
+/// the previous implementation: every match is pushed into a shared `Mutex`, then all the
+/// matches are sorted
+fn mutex_full_sort(
+    pool: &rayon::ThreadPool,
+    query: &str,
+    haystack: &[String],
+    n: usize,
+) -> Vec<usize> {
+    let matcher = SkimMatcherV2::default();
+    let matched = Mutex::new(Vec::new());
+    let chunk_size = 50;
+    pool.install(|| {
+        haystack
+            .par_chunks(chunk_size)
+            .enumerate()
+            .for_each(|(i, chunk)| {
+                let base_idx = i * chunk_size;
+                chunk.iter().enumerate().for_each(|(i, line)| {
+                    if let Some((score, indices)) = matcher.fuzzy_indices(line, query) {
+                        if score > 0 {
+                            matched.lock().unwrap().push((i + base_idx, score, indices));
+                        }
+                    }
+                });
+            });
+    });
+    let mut matched = matched.into_inner().unwrap();
+    matched.sort_by_key(|m| std::cmp::Reverse(m.1));
+    matched.into_iter().take(n).map(|(idx, _, _)| idx).collect()
+}
+
+fn bench_matching(c: &mut Criterion) {
+    let haystack = synthetic_lines();
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(num_cpus::get())
+            .build()
+            .unwrap(),
+    );
+
+    let mut group = c.benchmark_group("match 1M lines");
+    group.sample_size(10);
+    // a query matching most of the lines stresses the ranking, a rare one stresses matching
+    for query in ["a", "eta", "kappa_9"] {
+        group.bench_with_input(BenchmarkId::new("mutex_full_sort", query), query, |b, q| {
+            b.iter(|| mutex_full_sort(&pool, q, &haystack, TOP_N))
+        });
+        group.bench_with_input(BenchmarkId::new("termenu", query), query, |b, q| {
+            b.iter(|| termenu::bench_match(pool.clone(), q, &haystack, TOP_N))
+        });
+    }
+    group.finish();
+}
+
+fn bench_ranking(c: &mut Criterion) {
+    let haystack = synthetic_lines();
+    let matcher = SkimMatcherV2::default();
+    let matched: Vec<(usize, i64, Vec<usize>)> = haystack
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let (score, indices) = matcher.fuzzy_indices(line, "a")?;
+            Some((i, score, indices))
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("rank 1M matches");
+    group.sample_size(10);
+    group.bench_function("full_sort", |b| {
+        b.iter_batched_ref(
+            || matched.clone(),
+            |matched| matched.sort_by_key(|m| std::cmp::Reverse(m.1)),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("termenu", |b| {
+        b.iter_batched_ref(
+            || matched.clone(),
+            |matched| termenu::bench_rank(matched, TOP_N),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_matching, bench_ranking);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use futures_util::stream;
+use termenu::{Item, Menu};
+
+/// Try this:
+/// ```bash
+/// cargo run --example async --features async
+/// ```
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let mut menu = Menu::new().unwrap();
+
+    // items keep coming while the menu is open
+    let items = stream::unfold(1, |i| async move {
+        if i > 50 {
+            return None;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Some((Item::new(format!("{i}th item").as_str(), i), i + 1))
+    });
+
+    // give up after 30 seconds
+    let timeout = tokio::time::sleep(Duration::from_secs(30));
+
+    let selection = menu
+        .set_title("test selection:")
+        .select_async(items, timeout)
+        .await
+        .unwrap();
+    if let Some(selection) = selection {
+        println!("You selected: {selection}");
+    } else {
+        println!("You didn't select anything");
+    }
+}
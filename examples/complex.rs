@@ -34,7 +34,7 @@ fn main() {
             FontStyle::default()
                 .set_shape(FontShape::Underline)
                 .set_fg_color(colored::Color::Black)
-                .set_bg_color_256((215, 255, 0))
+                .set_bg_color_rgb((215, 255, 0))
                 .build(),
         )
         .set_more_tag_style(
@@ -1,3 +1,5 @@
+use termenu::{KeyCode, KeyEvent, KeyModifiers, Response};
+
 fn main() {
     let mut menu = termenu::Menu::new().unwrap();
     let mut item_list = Vec::new();
@@ -9,6 +11,28 @@ fn main() {
             },
         ));
     }
+
+    // callbacks run while the menu is open
+    let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+    menu.on_key(ctrl('y'), |ctx| {
+        if let Some(alias) = ctx.current().map(|item| item.alias.clone()) {
+            let _ = ctx.copy_to_clipboard(&alias);
+            ctx.set_status(&format!("copied: {alias}"));
+        }
+        Response::Continue
+    })
+    .on_key(ctrl('x'), |ctx| {
+        ctx.remove_current();
+        Response::Continue
+    })
+    .on_key(ctrl('r'), |ctx| {
+        if let Some(alias) = ctx.current().map(|item| item.alias.to_uppercase()) {
+            ctx.rename_current(&alias);
+        }
+        Response::Continue
+    })
+    .on_key(ctrl('q'), |_| Response::ExitWith(2));
+
     let selection = menu
         .set_title("test selection:")
         .add_list(item_list)
@@ -16,5 +40,9 @@ fn main() {
         .unwrap();
     if let Some(selection) = selection {
         selection();
+    } else if let Some(code) = menu.exit_code() {
+        // the terminal is restored when the menu is dropped
+        drop(menu);
+        std::process::exit(code);
     }
 }
@@ -11,13 +11,14 @@ fn run_once(menu: &mut termenu::Menu<i32>) {
     for i in 1..=10 {
         item_list.push(termenu::Item::new(format!("{i}th item").as_str(), i));
     }
-    let selection = menu
-        .set_title("test selection:")
+    menu.set_title("test selection:")
         .add_list(item_list)
         .select()
         .unwrap();
-    if let Some(selection) = selection {
-        println!("You selected: {selection}");
+
+    // move the selected item out, so it can outlive the current run
+    if let Some(selection) = menu.take_selection() {
+        println!("You selected: {}", selection.value);
     } else {
         println!("You didn't select anything");
     }
@@ -0,0 +1,155 @@
+use std::{
+    future::{poll_fn, Future},
+    io,
+    ops::{Deref, DerefMut},
+    pin::pin,
+};
+
+use crossterm::event::EventStream;
+use futures_util::{select, FutureExt, Stream, StreamExt};
+
+use crate::{keymap::KeyResponse, Item, Menu};
+
+// max number of items appended between two redraws while feeding items from a stream
+const ITEM_BATCH_SIZE: usize = 1024;
+
+// restores the terminal when it's dropped, so it's restored even if the select future returns
+// early with an error or is dropped before it completes
+struct Running<'a, T>(&'a mut Menu<T>);
+
+impl<T> Deref for Running<'_, T> {
+    type Target = Menu<T>;
+
+    fn deref(&self) -> &Menu<T> {
+        self.0
+    }
+}
+
+impl<T> DerefMut for Running<'_, T> {
+    fn deref_mut(&mut self) -> &mut Menu<T> {
+        self.0
+    }
+}
+
+impl<T> Drop for Running<'_, T> {
+    fn drop(&mut self) {
+        let _ = self.0.clear();
+        let _ = self.0.stop();
+    }
+}
+
+// async select api, enabled by the `async` feature
+impl<T> Menu<T> {
+    /// Async version of [select](Self::select), it reads terminal events from crossterm's
+    /// [`EventStream`] instead of blocking the thread.
+    ///
+    /// Items yielded by `items` are appended to the menu while it is open, the menu starts even
+    /// if no item has been added yet. Pass [`futures_util::stream::empty()`] if there is nothing
+    /// to feed.
+    ///
+    /// The menu exits and returns `None` as soon as `cancel` completes. Pass
+    /// [`std::future::pending()`] if it should never be cancelled.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() {
+    /// use futures_util::stream;
+    /// use termenu::{Item, Menu};
+    ///
+    /// let mut menu = Menu::new().unwrap();
+    /// let items = stream::iter((1..=10).map(|i| Item::new(&format!("{i}th item"), i)));
+    /// let selection = menu
+    ///     .set_title("test selection:")
+    ///     .select_async(items, std::future::pending())
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn select_async<S, F>(&mut self, items: S, cancel: F) -> io::Result<Option<&T>>
+    where
+        S: Stream<Item = Item<T>>,
+        F: Future<Output = ()>,
+    {
+        let idx = self.select_async0(items, cancel).await?;
+        Ok(idx.map(|idx| &self.item_list[idx].value))
+    }
+
+    /// Same as [select_async](Self::select_async), but return the selected [Item] instead of the
+    /// value
+    pub async fn select_item_async<S, F>(
+        &mut self,
+        items: S,
+        cancel: F,
+    ) -> io::Result<Option<&Item<T>>>
+    where
+        S: Stream<Item = Item<T>>,
+        F: Future<Output = ()>,
+    {
+        let idx = self.select_async0(items, cancel).await?;
+        Ok(idx.map(|idx| &self.item_list[idx]))
+    }
+
+    async fn select_async0<S, F>(&mut self, items: S, cancel: F) -> io::Result<Option<usize>>
+    where
+        S: Stream<Item = Item<T>>,
+        F: Future<Output = ()>,
+    {
+        self.selection = None;
+        self.apply_updates();
+        let mut events = EventStream::new().fuse();
+        let mut items = pin!(items.ready_chunks(ITEM_BATCH_SIZE).fuse());
+        let mut cancel = pin!(cancel.fuse());
+
+        let mut menu = Running(self);
+        // more items may arrive later, so reserve as many rows as the max height allows
+        menu.start(usize::MAX)?;
+        let progress = menu.match_worker().signal();
+        let updates = menu.handle_shared.clone();
+        loop {
+            let resp = select! {
+                evt = events.next() => match evt {
+                    Some(evt) => menu.dispatch_event(evt?)?,
+                    None => break,
+                },
+                batch = items.next() => match batch {
+                    Some(batch) => menu.extend_while_open(batch)?,
+                    None => continue,
+                },
+                _ = poll_fn(|cx| progress.poll_ready(cx)).fuse() => {
+                    KeyResponse::new(false, menu.receive_matches())
+                },
+                _ = poll_fn(|cx| updates.poll_ready(cx)).fuse() => {
+                    KeyResponse::new(false, menu.apply_updates())
+                },
+                _ = cancel => {
+                    menu.selected = false;
+                    break;
+                },
+            };
+            if resp.exit() {
+                break;
+            }
+            if resp.redraw() {
+                menu.draw()?;
+            }
+        }
+        drop(menu);
+        Ok(self.get_selection())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream;
+
+    use crate::{Item, Menu};
+
+    fn assert_send<S: Send>(_: &S) {}
+
+    #[test]
+    fn select_async_is_send() {
+        let mut menu = Menu::<i32>::with_size(false, 0, 80, 24);
+        let future = menu.select_async(stream::empty::<Item<i32>>(), std::future::pending());
+        assert_send(&future);
+    }
+}
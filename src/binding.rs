@@ -0,0 +1,242 @@
+use std::io;
+
+use base64::Engine;
+use crossterm::{event::KeyEvent, terminal};
+
+use crate::{
+    keymap::{normalize, KeyResponse},
+    macros::*,
+    Item, Menu, Mode,
+};
+
+pub(crate) type Callback<T> = dyn FnMut(&mut MenuContext<'_, T>) -> Response + Send;
+
+// the callback is taken out while it runs
+pub(crate) type Binding<T> = (KeyEvent, Option<Box<Callback<T>>>);
+
+/// what the menu does after a key callback returns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// keep the menu open
+    Continue,
+    /// select the item under the cursor, like Enter
+    Select,
+    /// close the menu without selecting anything, like Esc
+    Exit,
+    /// close the menu without selecting anything, the code can be read with
+    /// [Menu::exit_code] afterwards
+    ExitWith(i32),
+}
+
+/// the open menu as seen by a key callback, see [Menu::on_key]
+pub struct MenuContext<'a, T> {
+    menu: &'a mut Menu<T>,
+}
+
+impl<T> MenuContext<'_, T> {
+    /// The item under the cursor, if any
+    pub fn current(&self) -> Option<&Item<T>> {
+        self.menu.item_at(self.menu.cursor_idx())
+    }
+
+    /// The query typed in query mode, empty in normal mode
+    pub fn query(&self) -> &str {
+        self.menu.query().unwrap_or("")
+    }
+
+    /// The marked items in the order they're listed, see [Menu::enable_multi_select]
+    pub fn marked(&self) -> Vec<&Item<T>> {
+        self.menu.marked()
+    }
+
+    /// Remove the item under the cursor along with its children, the cursor moves to the next
+    /// item
+    pub fn remove_current(&mut self) {
+        if let Some(current) = self.menu.item_idx_at(self.menu.cursor_idx()) {
+            self.menu.remove_while_open(|idx, _| idx == current);
+        }
+    }
+
+    /// Change the alias of the item under the cursor, the query is re-run if there is one.
+    ///
+    /// The [keywords](Item::keywords) are kept, and a [search text](Item::search_text) is left
+    /// as it is.
+    pub fn rename_current(&mut self, alias: &str) {
+        if let Some(current) = self.menu.item_idx_at(self.menu.cursor_idx()) {
+            self.menu.item_list[current].alias = alias.to_string();
+            self.menu.invalidate_query_cache();
+            self.menu.restore_cursor(Some(current));
+        }
+    }
+
+    /// Display a message in place of the more/end tag, until the next key is pressed
+    pub fn set_status(&mut self, status: &str) {
+        self.menu.status = Some(status.to_string());
+    }
+
+    /// Copy the text to the system clipboard with the OSC 52 escape sequence. It's up to the
+    /// terminal to allow it, and it works over ssh as well.
+    pub fn copy_to_clipboard(&mut self, text: &str) -> io::Result<()> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+        term_printf!("\x1b]52;c;{}\x07", encoded);
+        Ok(())
+    }
+
+    /// Replace all the items of the menu. The query is kept and re-run, and the cursor stays on
+    /// the item with the same alias if there is one.
+    pub fn replace_items(&mut self, items: Vec<Item<T>>) {
+        self.menu.replace_while_open(items);
+    }
+
+    /// Run `f` with the terminal restored, e.g. to run a command that writes to the terminal or
+    /// reads from it. The menu is drawn again below whatever `f` prints.
+    pub fn suspend<R>(&mut self, f: impl FnOnce() -> R) -> io::Result<R> {
+        self.menu.suspend()?;
+        let result = f();
+        self.menu.resume()?;
+        Ok(result)
+    }
+}
+
+// key callbacks
+impl<T> Menu<T> {
+    /// Call `callback` when the given key is pressed while the menu is open, instead of what the
+    /// key does by default. Binding a key again replaces its callback.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use termenu::{KeyCode, KeyEvent, KeyModifiers, Item, Menu, Response};
+    ///
+    /// let mut menu = Menu::new().unwrap();
+    /// menu.add(Item::new("first", 1)).on_key(
+    ///     KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+    ///     |ctx| {
+    ///         ctx.replace_items(vec![Item::new("reloaded", 2)]);
+    ///         Response::Continue
+    ///     },
+    /// );
+    /// ```
+    pub fn on_key<F>(&mut self, key: KeyEvent, callback: F) -> &mut Self
+    where
+        F: FnMut(&mut MenuContext<'_, T>) -> Response + Send + 'static,
+    {
+        let key = normalize(key);
+        self.key_bindings.retain(|(bound, _)| *bound != key);
+        self.key_bindings.push((key, Some(Box::new(callback))));
+        self
+    }
+
+    /// The code a key callback exited the last run with by returning [Response::ExitWith],
+    /// `None` if it ended in any other way
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// run the callback bound to the key, `None` if the key isn't bound
+    pub(crate) fn dispatch_binding(&mut self, key: KeyEvent) -> io::Result<Option<KeyResponse>> {
+        let key = normalize(key);
+        let pos = match self
+            .key_bindings
+            .iter()
+            .position(|(bound, _)| *bound == key)
+        {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+
+        // the callback borrows the menu mutably, so take it out while it runs
+        let mut callback = match self.key_bindings[pos].1.take() {
+            Some(callback) => callback,
+            None => return Ok(None),
+        };
+        let response = callback(&mut MenuContext { menu: self });
+        if let Some(slot) = self
+            .key_bindings
+            .iter_mut()
+            .find(|(bound, _)| *bound == key)
+        {
+            slot.1.get_or_insert(callback);
+        }
+
+        let resp = match response {
+            Response::Continue => KeyResponse::new(false, true),
+            Response::Select => self.key_enter()?,
+            Response::Exit => {
+                self.selected = false;
+                KeyResponse::new(true, false)
+            }
+            Response::ExitWith(code) => {
+                self.selected = false;
+                self.exit_code = Some(code);
+                KeyResponse::new(true, false)
+            }
+        };
+        Ok(Some(resp))
+    }
+
+    // give the terminal back while the menu is open
+    fn suspend(&mut self) -> io::Result<()> {
+        self.clear()?;
+        self.stop()
+    }
+
+    // take the terminal again after `suspend`, the menu is drawn from where the cursor is now
+    fn resume(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        if self.enable_mouse {
+            term_exec!(crossterm::event::EnableMouseCapture);
+        }
+        if let Mode::Normal = self.mode {
+            term_exec!(crossterm::cursor::Hide);
+        }
+        self.relocate()?;
+        self.set_max_height(self.max_height_percent);
+        self.scroll_to_fit(self.row_count())?;
+        // keep the cursor visible in case the menu got shorter
+        self.move_to(self.cursor_idx())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MenuContext;
+    use crate::{Item, Menu};
+
+    #[test]
+    fn rename_updates_search_text() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(vec![
+            // the keywords begin like the alias
+            Item::new("old", 1).with_keywords(&["old-tag"]),
+            Item::new("other", 2).with_search_text("unrelated"),
+        ]);
+        let mut ctx = MenuContext { menu: &mut menu };
+        ctx.rename_current("new");
+        let item = ctx.current().unwrap();
+        assert_eq!(item.alias, "new");
+        assert_eq!(item.search_text(), "new old-tag");
+
+        menu.move_to(1).unwrap();
+        let mut ctx = MenuContext { menu: &mut menu };
+        ctx.rename_current("renamed");
+        assert_eq!(ctx.current().unwrap().search_text(), "unrelated");
+    }
+
+    #[test]
+    fn context_sees_the_marks() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(vec![
+            Item::new("a", 1),
+            Item::new("b", 2),
+            Item::new("c", 3),
+        ])
+        .enable_multi_select(true)
+        .set_marked_by(|item| item.value != 2);
+        menu.apply_initial_state().unwrap();
+        let mut ctx = MenuContext { menu: &mut menu };
+        ctx.remove_current();
+        let marked: Vec<_> = ctx.marked().iter().map(|item| item.value).collect();
+        assert_eq!(marked, [3]);
+    }
+}
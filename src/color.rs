@@ -1,69 +1,279 @@
-use std::ops;
+use std::{borrow::Cow, ops, str::FromStr};
 
-use crate::{ColorScheme, FontShape, FontStyle, Item, Menu};
+use crate::{ColorDepth, ColorScheme, FontShape, FontStyle, Item, Menu};
 use colored::*;
 
-pub(crate) fn colorize(s: &str, style: &FontStyle) -> String {
-    let mut colored = s.to_string();
+pub(crate) fn colorize(s: &str, style: &FontStyle, depth: ColorDepth) -> String {
+    match sgr(style, depth) {
+        Some(seq) => format!("{}{}\x1b[0m", seq, s),
+        None => s.to_string(),
+    }
+}
+
+// apply the style to a line with colored parts in it, pad it with `pad` spaces first, so the
+// background fills the rest of the line. the style is restored after each colored part
+pub(crate) fn colorize_line(
+    line: &str,
+    pad: usize,
+    style: &FontStyle,
+    depth: ColorDepth,
+) -> String {
+    match sgr(style, depth) {
+        Some(seq) => format!(
+            "{}{}{}\x1b[0m",
+            seq,
+            line.replace("\x1b[0m", &format!("\x1b[0m{}", seq)),
+            " ".repeat(pad)
+        ),
+        None => line.to_string(),
+    }
+}
+
+// the escape sequence that applies the style, `None` if it changes nothing
+fn sgr(style: &FontStyle, depth: ColorDepth) -> Option<String> {
+    if !colored::control::SHOULD_COLORIZE.should_colorize() {
+        return None;
+    }
+
+    let mut codes: Vec<Cow<str>> = Vec::new();
 
     // shape
     if let Some(shape) = style.shape {
         if shape & FontShape::Bold != 0 {
-            colored = colored.bold().to_string();
+            codes.push("1".into());
         }
         if shape & FontShape::Italic != 0 {
-            colored = colored.italic().to_string();
+            codes.push("3".into());
         }
         if shape & FontShape::Underline != 0 {
-            colored = colored.underline().to_string();
+            codes.push("4".into());
         }
     }
 
     // fg
-    if let Some(fg_color) = style.fg_color {
-        colored = colored.color(fg_color).to_string();
-    } else if let Some(fg_color_256) = style.fg_color_256 {
-        colored = colored
-            .truecolor(fg_color_256.0, fg_color_256.1, fg_color_256.2)
-            .to_string();
+    if let Some(fg_color) = style.fg_color.and_then(|c| c.downsample(depth)) {
+        codes.push(fg_color.sgr(false));
     }
 
     // bg
-    if let Some(bg_color) = style.bg_color {
-        colored = colored.on_color(bg_color).to_string();
-    } else if let Some(bg_color_256) = style.bg_color_256 {
-        colored = colored
-            .on_truecolor(bg_color_256.0, bg_color_256.1, bg_color_256.2)
-            .to_string();
+    if let Some(bg_color) = style.bg_color.and_then(|c| c.downsample(depth)) {
+        codes.push(bg_color.sgr(true));
+    }
+
+    if codes.is_empty() {
+        return None;
     }
+    Some(format!("\x1b[{}m", codes.join(";")))
+}
+
+/// a color of a [FontStyle]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StyleColor {
+    Named(Color),
+    Ansi256(u8),
+    Rgb((u8, u8, u8)),
+}
+
+// RGB values of the 16 basic colors in xterm
+const ANSI16_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const ANSI16: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+// levels of each channel in the 6x6x6 color cube of the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl StyleColor {
+    // the closest color that can be displayed at the given depth
+    fn downsample(self, depth: ColorDepth) -> Option<StyleColor> {
+        let color = match (self, depth) {
+            (_, ColorDepth::NoColor) => return None,
+            // a 24-bit color of `colored` is downsampled like any other RGB color
+            (StyleColor::Named(Color::TrueColor { r, g, b }), _) => {
+                return StyleColor::Rgb((r, g, b)).downsample(depth)
+            }
+            (StyleColor::Named(_), _) | (_, ColorDepth::TrueColor) => self,
+            (StyleColor::Ansi256(_), ColorDepth::Ansi256) => self,
+            (StyleColor::Rgb(rgb), ColorDepth::Ansi256) => {
+                StyleColor::Ansi256(nearest_ansi256(rgb))
+            }
+            (StyleColor::Ansi256(idx), ColorDepth::Ansi16) if idx < 16 => {
+                StyleColor::Named(ANSI16[idx as usize])
+            }
+            (StyleColor::Ansi256(idx), ColorDepth::Ansi16) => {
+                StyleColor::Named(nearest_ansi16(ansi256_rgb(idx)))
+            }
+            (StyleColor::Rgb(rgb), ColorDepth::Ansi16) => StyleColor::Named(nearest_ansi16(rgb)),
+        };
+        Some(color)
+    }
+
+    // the SGR parameters that set the color
+    fn sgr(self, background: bool) -> Cow<'static, str> {
+        let base = if background { 48 } else { 38 };
+        match self {
+            StyleColor::Named(c) if background => c.to_bg_str(),
+            StyleColor::Named(c) => c.to_fg_str(),
+            StyleColor::Ansi256(idx) => format!("{};5;{}", base, idx).into(),
+            StyleColor::Rgb((r, g, b)) => format!("{};2;{};{};{}", base, r, g, b).into(),
+        }
+    }
+}
 
-    colored
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    let idx = (0..16)
+        .min_by_key(|&i| distance(rgb, ANSI16_RGB[i]))
+        .unwrap();
+    ANSI16[idx]
+}
+
+fn ansi256_rgb(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..=15 => ANSI16_RGB[idx as usize],
+        16..=231 => {
+            let i = idx - 16;
+            let level = |n: u8| CUBE_LEVELS[n as usize];
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let gray = 8 + (idx - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+// the closest color of the cube or the grayscale ramp, the 16 basic colors are skipped since
+// terminals often change them
+fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .unwrap() as u8
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + ((avg.saturating_sub(3)) / 10).min(23) as u8;
+    if distance(rgb, ansi256_rgb(gray)) < distance(rgb, ansi256_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+impl ColorDepth {
+    /// Detect the colors the terminal can display from the environment:
+    /// - `NO_COLOR` set to anything disables colors
+    /// - `COLORTERM=truecolor` or `24bit` means 24-bit colors
+    /// - a `TERM` with `256color` in it means 256 colors, `dumb` means none
+    /// - otherwise the 16 basic colors
+    pub fn detect() -> ColorDepth {
+        Self::from_env(|name| std::env::var(name).unwrap_or_default())
+    }
+
+    fn from_env(var: impl Fn(&str) -> String) -> ColorDepth {
+        if !var("NO_COLOR").is_empty() {
+            return ColorDepth::NoColor;
+        }
+        if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            return ColorDepth::TrueColor;
+        }
+        let term = var("TERM");
+        if term == "dumb" {
+            ColorDepth::NoColor
+        } else if term.ends_with("-direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else if cfg!(windows) && term.is_empty() {
+            // the Windows terminals that understand escape codes all support 24-bit colors
+            ColorDepth::TrueColor
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
 }
 
-impl<T: Send + Sync> Menu<T> {
+impl<T> Menu<T> {
     pub fn set_colorscheme(&mut self, cs: ColorScheme) -> &mut Self {
         self.colorscheme = cs;
         self
     }
+
+    /// Set the colors the terminal can display, colors of the colorscheme it can't display are
+    /// replaced with the closest ones. Default is [ColorDepth::detect]
+    pub fn set_color_depth(&mut self, depth: ColorDepth) -> &mut Self {
+        self.color_depth = depth;
+        self
+    }
 }
 
-impl<T: Send + Sync> Item<T> {
-    pub(crate) fn get_colored_alias_for_normal_mode(&self, colorscheme: &ColorScheme) -> String {
-        colorize(&self.alias, &colorscheme.items)
+impl<T> Item<T> {
+    // `alias` is the part of the alias to display, which is a prefix of it when it's truncated
+    pub(crate) fn get_colored_alias_for_normal_mode(
+        &self,
+        colorscheme: &ColorScheme,
+        depth: ColorDepth,
+        alias: &str,
+    ) -> String {
+        colorize(alias, &colorscheme.items, depth)
     }
 
-    pub(crate) fn get_colored_alias_for_query_mode(&self, colorscheme: &ColorScheme) -> String {
+    pub(crate) fn get_colored_alias_for_query_mode(
+        &self,
+        colorscheme: &ColorScheme,
+        depth: ColorDepth,
+        alias: &str,
+        indices: &[usize],
+    ) -> String {
+        let alias_chars: Vec<char> = alias.chars().collect();
+        let len = alias_chars.len();
+        let mut indices = self.alias_indices(indices);
+        indices.retain(|&idx| idx < len);
         // no matched indices
-        if self.matched_indices.is_none() || self.matched_indices.as_ref().unwrap().is_empty() {
-            return colorize(&self.alias, &colorscheme.items);
+        if indices.is_empty() {
+            return colorize(alias, &colorscheme.items, depth);
         }
 
         let mut display = String::new();
 
-        let indices = self.matched_indices.as_ref().unwrap();
-
-        let alias_chars: Vec<char> = self.alias.chars().collect();
-        let len = alias_chars.len();
         let mut left = 0;
 
         for &idx in indices.iter() {
@@ -73,7 +283,7 @@ impl<T: Send + Sync> Item<T> {
 
             // handle current char
             let cur_char: String = alias_chars[idx..=idx].iter().collect();
-            let ch = colorize(&cur_char, &colorscheme.matched);
+            let ch = colorize(&cur_char, &colorscheme.matched, depth);
             display.push_str(&ch);
 
             // update left
@@ -88,13 +298,41 @@ impl<T: Send + Sync> Item<T> {
 
         display
     }
+
+    // map the matched char indices of the search text to the alias, the chars matched outside
+    // of the alias are not highlighted
+    fn alias_indices(&self, indices: &[usize]) -> Vec<usize> {
+        let offset = match &self.search_text {
+            // the keywords, if any, follow the alias
+            None => 0,
+            Some(text) => match text.find(&self.alias) {
+                Some(byte_idx) => text[..byte_idx].chars().count(),
+                // no mapping, nothing to highlight
+                None => return Vec::new(),
+            },
+        };
+        let range = offset..offset + self.alias.chars().count();
+        indices
+            .iter()
+            .filter(|idx| range.contains(idx))
+            .map(|idx| idx - offset)
+            .collect()
+    }
 }
 
 impl ops::BitOr for FontShape {
-    type Output = Self; // 使用 u8 表示组合的位标志
+    type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self {
-        unsafe { std::mem::transmute((self as u8) | (rhs as u8)) }
+        match (self as u8) | (rhs as u8) {
+            1 => FontShape::Italic,
+            2 => FontShape::Bold,
+            3 => FontShape::BoldItalic,
+            4 => FontShape::Underline,
+            5 => FontShape::ItalicUnderline,
+            6 => FontShape::BoldUnderline,
+            _ => FontShape::BoldItalicUnderline,
+        }
     }
 }
 
@@ -109,11 +347,30 @@ impl ops::BitAnd for FontShape {
 impl FontStyle {
     fn default_matched() -> Self {
         Self {
-            shape: None,
-            fg_color: Some(Color::Yellow),
-            bg_color: None,
-            fg_color_256: None,
-            bg_color_256: None,
+            fg_color: Some(StyleColor::Named(Color::Yellow)),
+            ..Default::default()
+        }
+    }
+
+    fn default_header() -> Self {
+        Self {
+            shape: Some(FontShape::Bold),
+            ..Default::default()
+        }
+    }
+
+    fn default_disabled() -> Self {
+        Self {
+            shape: Some(FontShape::Italic),
+            fg_color: Some(StyleColor::Named(Color::BrightBlack)),
+            ..Default::default()
+        }
+    }
+
+    fn default_description() -> Self {
+        Self {
+            fg_color: Some(StyleColor::Named(Color::BrightBlack)),
+            ..Default::default()
         }
     }
 
@@ -123,35 +380,282 @@ impl FontStyle {
     }
 
     pub fn set_fg_color(&mut self, color: Color) -> &mut Self {
-        self.fg_color = Some(color);
+        self.fg_color = Some(StyleColor::Named(color));
         self
     }
 
     pub fn set_bg_color(&mut self, color: Color) -> &mut Self {
-        self.bg_color = Some(color);
+        self.bg_color = Some(StyleColor::Named(color));
         self
     }
 
-    pub fn set_fg_color_256(&mut self, color: (u8, u8, u8)) -> &mut Self {
-        self.fg_color_256 = Some(color);
+    /// set the fg color by RGB
+    pub fn set_fg_color_rgb(&mut self, color: (u8, u8, u8)) -> &mut Self {
+        self.fg_color = Some(StyleColor::Rgb(color));
         self
     }
 
-    pub fn set_bg_color_256(&mut self, color: (u8, u8, u8)) -> &mut Self {
-        self.bg_color_256 = Some(color);
+    /// set the bg color by RGB
+    pub fn set_bg_color_rgb(&mut self, color: (u8, u8, u8)) -> &mut Self {
+        self.bg_color = Some(StyleColor::Rgb(color));
+        self
+    }
+
+    /// set the fg color by its index in the 256-color palette
+    pub fn set_fg_color_ansi256(&mut self, index: u8) -> &mut Self {
+        self.fg_color = Some(StyleColor::Ansi256(index));
+        self
+    }
+
+    /// set the bg color by its index in the 256-color palette
+    pub fn set_bg_color_ansi256(&mut self, index: u8) -> &mut Self {
+        self.bg_color = Some(StyleColor::Ansi256(index));
         self
     }
 
+    #[deprecated(note = "it takes an RGB color, use `set_fg_color_rgb` instead")]
+    pub fn set_fg_color_256(&mut self, color: (u8, u8, u8)) -> &mut Self {
+        self.set_fg_color_rgb(color)
+    }
+
+    #[deprecated(note = "it takes an RGB color, use `set_bg_color_rgb` instead")]
+    pub fn set_bg_color_256(&mut self, color: (u8, u8, u8)) -> &mut Self {
+        self.set_bg_color_rgb(color)
+    }
+
     pub fn build(&mut self) -> Self {
         *self
     }
 }
 
+/// parse a style from attributes separated by spaces or `:`: `bold`, `italic`, `underline`, a
+/// foreground color and a background color after `on`, or prefixed with `on-`.
+///
+/// Colors are the 8 color names, optionally prefixed with `bright-`, `#rrggbb` or an index in
+/// the 256-color palette.
+///
+/// # Example
+/// ```
+/// # use termenu::FontStyle;
+/// let style: FontStyle = "bold underline bright-yellow on #262626".parse().unwrap();
+/// let style: FontStyle = "black:on:#d7ff00".parse().unwrap();
+/// let style: FontStyle = "black on-#d7ff00".parse().unwrap();
+/// ```
+impl FromStr for FontStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = FontStyle::default();
+        let mut attrs = s
+            .split(|c: char| c == ':' || c.is_whitespace())
+            .filter(|attr| !attr.is_empty());
+        while let Some(attr) = attrs.next() {
+            let shape = match attr {
+                "bold" => FontShape::Bold,
+                "italic" => FontShape::Italic,
+                "underline" => FontShape::Underline,
+                "on" => {
+                    let color = attrs.next().ok_or("missing color after 'on'")?;
+                    style.bg_color = Some(parse_color(color)?);
+                    continue;
+                }
+                _ => {
+                    match attr.strip_prefix("on-") {
+                        Some(color) => style.bg_color = Some(parse_color(color)?),
+                        None => style.fg_color = Some(parse_color(attr)?),
+                    }
+                    continue;
+                }
+            };
+            style.shape = Some(match style.shape {
+                Some(current) => current | shape,
+                None => shape,
+            });
+        }
+        Ok(style)
+    }
+}
+
+fn parse_color(s: &str) -> Result<StyleColor, String> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(StyleColor::Rgb((r, g, b))),
+            _ => Err(format!("invalid color '{}', expected #rrggbb", s)),
+        };
+    }
+    if let Ok(idx) = s.parse() {
+        return Ok(StyleColor::Ansi256(idx));
+    }
+    let (bright, name) = match s.strip_prefix("bright-") {
+        Some(name) => (true, name),
+        None => (false, s),
+    };
+    let color = match (bright, name) {
+        (false, "black") => Color::Black,
+        (false, "red") => Color::Red,
+        (false, "green") => Color::Green,
+        (false, "yellow") => Color::Yellow,
+        (false, "blue") => Color::Blue,
+        (false, "magenta") => Color::Magenta,
+        (false, "cyan") => Color::Cyan,
+        (false, "white") => Color::White,
+        (true, "black") => Color::BrightBlack,
+        (true, "red") => Color::BrightRed,
+        (true, "green") => Color::BrightGreen,
+        (true, "yellow") => Color::BrightYellow,
+        (true, "blue") => Color::BrightBlue,
+        (true, "magenta") => Color::BrightMagenta,
+        (true, "cyan") => Color::BrightCyan,
+        (true, "white") => Color::BrightWhite,
+        _ => return Err(format!("unknown style attribute '{}'", s)),
+    };
+    Ok(StyleColor::Named(color))
+}
+
+// names accepted by `ColorScheme::theme`
+const THEMES: [&str; 4] = ["dark", "light", "16-color", "monochrome"];
+
+// names of the elements in a colorscheme spec
+const ELEMENTS: [&str; 14] = [
+    "title",
+    "prompt",
+    "query",
+    "items",
+    "matched",
+    "pointer",
+    "marker",
+    "chosen",
+    "chosen-bg",
+    "more-tag",
+    "end-tag",
+    "description",
+    "header",
+    "disabled",
+];
+
+/// parse a colorscheme from an fzf-style spec: comma-separated `element:style` pairs, where the
+/// style is parsed by [FontStyle]'s `from_str`. A theme name (see [theme](ColorScheme::theme))
+/// replaces the whole colorscheme, so it usually comes first.
+///
+/// The elements are `title`, `prompt`, `query`, `items`, `matched`, `pointer`, `marker`,
+/// `chosen`, `chosen-bg`, `more-tag`, `end-tag`, `description`, `header` and `disabled`, `current`
+/// is an alias of `chosen`. Elements not in the spec keep their default style, `end-tag` follows
+/// `more-tag` and `marker` follows `pointer` unless they are set.
+///
+/// # Example
+/// ```
+/// # use termenu::ColorScheme;
+/// let cs: ColorScheme = "title:bold:green,matched:#ffaf00,chosen:black:on:#d7ff00"
+///     .parse()
+///     .unwrap();
+/// let cs: ColorScheme = "light,matched:underline:red".parse().unwrap();
+/// ```
+impl FromStr for ColorScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cs = ColorScheme::default();
+        cs.apply_spec(s)?;
+        Ok(cs)
+    }
+}
+
 impl ColorScheme {
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// A built-in colorscheme by name, `None` if there is no such theme
+    /// - `dark`: 24-bit colors for dark backgrounds
+    /// - `light`: 24-bit colors for light backgrounds
+    /// - `16-color`: the basic terminal colors only, which is the default
+    /// - `monochrome`: no colors, only bold, italic and underline
+    pub fn theme(name: &str) -> Option<ColorScheme> {
+        let spec = match name {
+            "dark" => {
+                "title:bold:#87afd7,prompt:#6c6c6c,query:#d7d7d7,matched:#ffaf00,\
+                 pointer:bold:#ff5f87,chosen:bold:#ffd75f,chosen-bg:on:#303030,\
+                 more-tag:#6c6c6c,description:#808080,header:bold:#5fafd7,\
+                 disabled:italic:#585858"
+            }
+            "light" => {
+                "title:bold:#005f87,prompt:#8a8a8a,query:#262626,matched:#d75f00,\
+                 pointer:bold:#d7005f,chosen:bold:#af5f00,chosen-bg:on:#e4e4e4,\
+                 more-tag:#8a8a8a,description:#6c6c6c,header:bold:#0087af,\
+                 disabled:italic:#a8a8a8"
+            }
+            "16-color" => return Some(ColorScheme::default()),
+            "monochrome" => {
+                "matched:underline,pointer:bold,chosen:bold:underline,description:italic,\
+                 header:bold,disabled:italic"
+            }
+            _ => return None,
+        };
+        // start from a blank scheme, so no default color leaks into the theme
+        let mut cs = ColorScheme {
+            matched: FontStyle::default(),
+            chosen_ln: FontStyle::default(),
+            description: FontStyle::default(),
+            header: FontStyle::default(),
+            disabled: FontStyle::default(),
+            ..ColorScheme::default()
+        };
+        cs.apply_spec(spec).expect("built-in themes are valid");
+        Some(cs)
+    }
+
+    // set the styles of a spec on top of the current ones, see `from_str`
+    fn apply_spec(&mut self, spec: &str) -> Result<(), String> {
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let (element, style) = match part.split_once(':') {
+                Some(pair) => pair,
+                None => {
+                    *self = ColorScheme::theme(part).ok_or_else(|| {
+                        format!(
+                            "unknown theme '{}', expected one of {}",
+                            part,
+                            THEMES.join(", ")
+                        )
+                    })?;
+                    continue;
+                }
+            };
+            let style: FontStyle = style.parse().map_err(|e| format!("{}: {}", element, e))?;
+            match element {
+                "title" => self.set_title_style(style),
+                "prompt" => self.set_prompt_style(style),
+                "query" => self.set_query_style(style),
+                "items" => self.set_items_style(style),
+                "matched" => self.set_matched_style(style),
+                "pointer" => self.set_pointer_style(style),
+                "marker" => self.set_marker_style(style),
+                "chosen" | "current" => self.set_chosen_ln_style(style),
+                "chosen-bg" => self.set_chosen_bg_style(style),
+                "more-tag" => self.set_more_tag_style(style),
+                "end-tag" => self.set_end_tag_style(style),
+                "description" => self.set_description_style(style),
+                "header" => self.set_header_style(style),
+                "disabled" => self.set_disabled_style(style),
+                _ => {
+                    return Err(format!(
+                        "unknown element '{}', expected one of {}",
+                        element,
+                        ELEMENTS.join(", ")
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+
     /// set the style of the title
     pub fn set_title_style(&mut self, style: FontStyle) -> &mut Self {
         self.title = style;
@@ -183,12 +687,71 @@ impl ColorScheme {
         self
     }
 
+    /// set the style of the item descriptions, which are drawn at the right of the items
+    pub fn set_description_style(&mut self, style: FontStyle) -> &mut Self {
+        self.description = style;
+        self
+    }
+
+    /// set the style of the group headers
+    pub fn set_header_style(&mut self, style: FontStyle) -> &mut Self {
+        self.header = style;
+        self
+    }
+
+    /// set the style of the disabled items
+    pub fn set_disabled_style(&mut self, style: FontStyle) -> &mut Self {
+        self.disabled = style;
+        self
+    }
+
     /// set the style of the '---more---' tag, which is displayed at the bottom of the menu when there are
     /// more items than the screen can display
     pub fn set_more_tag_style(&mut self, style: FontStyle) -> &mut Self {
         self.more_tag = style;
         self
     }
+
+    /// set the style of the '---end---' tag, which is displayed below the last item. Default is
+    /// the style of the '---more---' tag
+    pub fn set_end_tag_style(&mut self, style: FontStyle) -> &mut Self {
+        self.end_tag = Some(style);
+        self
+    }
+
+    /// set the style of the pointer, which is drawn in front of the chosen line
+    pub fn set_pointer_style(&mut self, style: FontStyle) -> &mut Self {
+        self.pointer = style;
+        self
+    }
+
+    /// set the style of the marker, which is drawn in front of the marked items. Default is the
+    /// style of the pointer
+    pub fn set_marker_style(&mut self, style: FontStyle) -> &mut Self {
+        self.marker = Some(style);
+        self
+    }
+
+    pub(crate) fn end_tag_style(&self) -> &FontStyle {
+        self.end_tag.as_ref().unwrap_or(&self.more_tag)
+    }
+
+    pub(crate) fn marker_style(&self) -> &FontStyle {
+        self.marker.as_ref().unwrap_or(&self.pointer)
+    }
+
+    /// set the style of the prompt, which is drawn between the title and the query in query mode
+    pub fn set_prompt_style(&mut self, style: FontStyle) -> &mut Self {
+        self.prompt = style;
+        self
+    }
+
+    /// set the style of the whole chosen line, from the pointer to the right edge of the
+    /// terminal, e.g. a background color. It's drawn under the styles of the parts of the line
+    pub fn set_chosen_bg_style(&mut self, style: FontStyle) -> &mut Self {
+        self.chosen_bg = style;
+        self
+    }
 }
 
 impl Default for ColorScheme {
@@ -200,6 +763,251 @@ impl Default for ColorScheme {
             matched: FontStyle::default_matched(),
             chosen_ln: FontStyle::default_matched(),
             more_tag: FontStyle::default(),
+            end_tag: None,
+            pointer: FontStyle::default(),
+            marker: None,
+            prompt: FontStyle::default(),
+            chosen_bg: FontStyle::default(),
+            description: FontStyle::default_description(),
+            header: FontStyle::default_header(),
+            disabled: FontStyle::default_disabled(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use colored::Color;
+
+    use super::StyleColor::{self, Ansi256, Named, Rgb};
+    use super::{nearest_ansi16, nearest_ansi256};
+    use crate::{ColorDepth, ColorScheme, FontStyle};
+
+    type Parts = (Option<u8>, Option<StyleColor>, Option<StyleColor>);
+
+    fn parts(style: &FontStyle) -> Parts {
+        (
+            style.shape.map(|shape| shape as u8),
+            style.fg_color,
+            style.bg_color,
+        )
+    }
+
+    fn parse(s: &str) -> Parts {
+        parts(&s.parse::<FontStyle>().unwrap())
+    }
+
+    #[test]
+    fn parse_font_style() {
+        assert_eq!(parse(""), (None, None, None));
+        assert_eq!(parse("bold:italic:underline"), (Some(7), None, None));
+        assert_eq!(
+            parse("underline bright-yellow on #262626"),
+            (
+                Some(4),
+                Some(Named(Color::BrightYellow)),
+                Some(Rgb((38, 38, 38)))
+            )
+        );
+        assert_eq!(
+            parse("black:on:236"),
+            (None, Some(Named(Color::Black)), Some(Ansi256(236)))
+        );
+        assert_eq!(
+            parse("black on-#d7ff00"),
+            (None, Some(Named(Color::Black)), Some(Rgb((215, 255, 0))))
+        );
+        // the color set last wins
+        assert_eq!(parse("red:#ff0000"), (None, Some(Rgb((255, 0, 0))), None));
+
+        for invalid in [
+            "on",
+            "bold:on",
+            "#fff",
+            "#gg0000",
+            "256",
+            "purple",
+            "bright-",
+            "on-",
+            "on-purple",
+        ] {
+            assert!(invalid.parse::<FontStyle>().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn parse_colorscheme_spec() {
+        let cs: ColorScheme =
+            "title:bold:green, matched:#ffaf00,chosen:black:on:#d7ff00,more-tag:red"
+                .parse()
+                .unwrap();
+        assert_eq!(parts(&cs.title), (Some(2), Some(Named(Color::Green)), None));
+        assert_eq!(parts(&cs.matched), (None, Some(Rgb((255, 175, 0))), None));
+        assert_eq!(
+            parts(&cs.chosen_ln),
+            (None, Some(Named(Color::Black)), Some(Rgb((215, 255, 0))))
+        );
+        assert_eq!(parts(cs.end_tag_style()), parts(&cs.more_tag));
+        assert_eq!(parts(cs.marker_style()), parts(&cs.pointer));
+        // `current` is the former name of `chosen`
+        let current: ColorScheme = "current:black:on:#d7ff00".parse().unwrap();
+        assert_eq!(parts(&current.chosen_ln), parts(&cs.chosen_ln));
+        // elements not in the spec keep their default style
+        let default = ColorScheme::default();
+        assert_eq!(parts(&cs.description), parts(&default.description));
+        // and keep their own styles once set, whatever the order
+        let cs: ColorScheme = "end-tag:blue,marker:bold,more-tag:red".parse().unwrap();
+        assert_eq!(
+            parts(cs.end_tag_style()),
+            (None, Some(Named(Color::Blue)), None)
+        );
+        assert_eq!(parts(&cs.more_tag), (None, Some(Named(Color::Red)), None));
+        assert_eq!(parts(cs.marker_style()), (Some(2), None, None));
+    }
+
+    #[test]
+    fn themes_replace_the_colorscheme() {
+        let cs: ColorScheme = "title:red,monochrome,matched:blue".parse().unwrap();
+        let monochrome = ColorScheme::theme("monochrome").unwrap();
+        assert_eq!(parts(&cs.title), parts(&monochrome.title));
+        // a style replaces the one of the theme as a whole
+        assert_eq!(parts(&cs.matched), (None, Some(Named(Color::Blue)), None));
+
+        for theme in ["dark", "light", "16-color", "monochrome"] {
+            assert!(theme.parse::<ColorScheme>().is_ok(), "{theme:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_colorscheme_specs() {
+        for (spec, error) in [
+            ("solarized", "unknown theme 'solarized'"),
+            ("cursor:red", "unknown element 'cursor'"),
+            ("title:on", "title: missing color after 'on'"),
+        ] {
+            let e = spec.parse::<ColorScheme>().err().unwrap();
+            assert!(e.starts_with(error), "{spec:?}: {e}");
+        }
+    }
+
+    #[test]
+    fn nearest_ansi256_colors() {
+        for (rgb, idx) in [
+            ((0, 0, 0), 16),
+            ((255, 255, 255), 231),
+            ((255, 0, 0), 196),
+            ((0, 95, 135), 24),
+            // halfway between the first two levels of the cube
+            ((47, 0, 255), 21),
+            ((48, 0, 255), 57),
+            // grays between black and the start of the ramp
+            ((4, 4, 4), 16),
+            ((5, 5, 5), 232),
+            ((8, 8, 8), 232),
+            ((47, 47, 47), 236),
+            ((128, 128, 128), 244),
+            // grays between the end of the ramp and white
+            ((238, 238, 238), 255),
+            ((246, 246, 246), 255),
+            ((250, 250, 250), 231),
+        ] {
+            assert_eq!(nearest_ansi256(rgb), idx, "{rgb:?}");
+        }
+    }
+
+    #[test]
+    fn nearest_ansi16_colors() {
+        for (rgb, color) in [
+            ((0, 0, 0), Color::Black),
+            ((255, 255, 255), Color::BrightWhite),
+            ((63, 63, 63), Color::Black),
+            ((64, 64, 64), Color::BrightBlack),
+            ((192, 192, 192), Color::White),
+            ((229, 0, 0), Color::Red),
+            ((231, 0, 0), Color::BrightRed),
+            ((0, 0, 200), Color::Blue),
+            ((100, 100, 255), Color::BrightBlue),
+        ] {
+            assert_eq!(nearest_ansi16(rgb), color, "{rgb:?}");
+        }
+    }
+
+    #[test]
+    fn downsample_colors() {
+        let truecolor = Named(Color::TrueColor { r: 255, g: 0, b: 0 });
+        for (color, depth, expected) in [
+            (
+                Rgb((255, 0, 0)),
+                ColorDepth::TrueColor,
+                Some(Rgb((255, 0, 0))),
+            ),
+            (Rgb((255, 0, 0)), ColorDepth::Ansi256, Some(Ansi256(196))),
+            (
+                Rgb((255, 0, 0)),
+                ColorDepth::Ansi16,
+                Some(Named(Color::BrightRed)),
+            ),
+            (
+                Ansi256(9),
+                ColorDepth::Ansi16,
+                Some(Named(Color::BrightRed)),
+            ),
+            (
+                Ansi256(244),
+                ColorDepth::Ansi16,
+                Some(Named(Color::BrightBlack)),
+            ),
+            (
+                Named(Color::Red),
+                ColorDepth::Ansi16,
+                Some(Named(Color::Red)),
+            ),
+            (Named(Color::Red), ColorDepth::NoColor, None),
+            (truecolor, ColorDepth::Ansi256, Some(Ansi256(196))),
+            (truecolor, ColorDepth::Ansi16, Some(Named(Color::BrightRed))),
+        ] {
+            assert_eq!(color.downsample(depth), expected, "{color:?} {depth:?}");
+        }
+    }
+
+    #[test]
+    fn detect_color_depth() {
+        for (env, depth) in [
+            (&[][..], ColorDepth::Ansi16),
+            (&[("TERM", "xterm")], ColorDepth::Ansi16),
+            (&[("TERM", "xterm-256color")], ColorDepth::Ansi256),
+            (&[("TERM", "xterm-direct")], ColorDepth::TrueColor),
+            (&[("TERM", "dumb")], ColorDepth::NoColor),
+            (
+                &[("TERM", "xterm"), ("COLORTERM", "24bit")],
+                ColorDepth::TrueColor,
+            ),
+            // COLORTERM wins over TERM, NO_COLOR over both
+            (
+                &[("TERM", "dumb"), ("COLORTERM", "truecolor")],
+                ColorDepth::TrueColor,
+            ),
+            (
+                &[("TERM", "xterm-256color"), ("NO_COLOR", "1")],
+                ColorDepth::NoColor,
+            ),
+            (
+                &[("COLORTERM", "truecolor"), ("NO_COLOR", "1")],
+                ColorDepth::NoColor,
+            ),
+            // an empty NO_COLOR is ignored
+            (
+                &[("TERM", "xterm-256color"), ("NO_COLOR", "")],
+                ColorDepth::Ansi256,
+            ),
+        ] {
+            let var = |name: &str| {
+                let value = env.iter().find(|(key, _)| *key == name);
+                value
+                    .map(|(_, value)| value.to_string())
+                    .unwrap_or_default()
+            };
+            assert_eq!(ColorDepth::from_env(var), depth, "{env:?}");
         }
     }
 }
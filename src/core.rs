@@ -1,34 +1,134 @@
-use std::io::{self, IsTerminal};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    io::{self, IsTerminal},
+    sync::Arc,
+    task::Context,
+};
 
 use crossterm::terminal;
-use once_cell::sync::OnceCell;
 
-use crate::{color::colorize, macros::*, term, ColorScheme, Item, Menu, Mode};
+use crate::{
+    color::colorize,
+    events::{EventReader, Wakeup},
+    handle,
+    keymap::KeyResponse,
+    macros::*,
+    query::{self, MatchState, Ranking},
+    term, tree, ColorDepth, ColorScheme, Item, KeyEvent, Menu, Mode,
+};
 
-impl<T: Send + Sync> Item<T> {
+impl<T> Item<T> {
     pub fn new(display: &str, value: T) -> Item<T> {
         Item {
             alias: display.to_string(),
             value,
-            score: None,
-            matched_indices: None,
+            weight: 0,
+            search_text: None,
+            keywords: Vec::new(),
+            description: None,
+            group: None,
+            enabled: true,
+            disabled_reason: None,
+            children: Vec::new(),
+            depth: 0,
+            id: 0,
         }
     }
+
+    /// Set the weight of the item, which is added to its match score in query mode
+    ///
+    /// # Example
+    /// ```
+    /// let item = termenu::Item::new("frequently used", 1).with_weight(100);
+    /// assert_eq!(item.weight, 100);
+    /// ```
+    pub fn with_weight(mut self, weight: i64) -> Item<T> {
+        self.weight = weight;
+        self
+    }
+
+    /// Match the query against the given text instead of the alias
+    pub fn with_search_text(mut self, text: &str) -> Item<T> {
+        self.search_text = Some(text.to_string());
+        self
+    }
+
+    /// Match the query against the alias followed by the given keywords, so the item can be
+    /// found by tags, IDs or synonyms that are not displayed. They're ignored if a
+    /// [search text](Self::with_search_text) is set.
+    ///
+    /// # Example
+    /// ```
+    /// let item = termenu::Item::new("Settings", 1).with_keywords(&["preferences", "config"]);
+    /// assert_eq!(item.keywords, ["preferences", "config"]);
+    /// ```
+    pub fn with_keywords(mut self, keywords: &[&str]) -> Item<T> {
+        self.keywords = keywords.iter().map(|keyword| keyword.to_string()).collect();
+        self
+    }
+
+    /// Set the description of the item, which is drawn dimmed at the right of the alias
+    ///
+    /// # Example
+    /// ```
+    /// let item = termenu::Item::new("Open File", 1).with_description("Ctrl+O");
+    /// assert_eq!(item.description.as_deref(), Some("Ctrl+O"));
+    /// ```
+    pub fn with_description(mut self, description: &str) -> Item<T> {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Disable the item, it's displayed but can't be selected, and the cursor skips it. The
+    /// reason is displayed when trying to select it.
+    ///
+    /// # Example
+    /// ```
+    /// let item = termenu::Item::new("production", 1).disabled(Some("locked by a deploy"));
+    /// assert!(!item.enabled);
+    /// ```
+    pub fn disabled(mut self, reason: Option<&str>) -> Item<T> {
+        self.enabled = false;
+        self.disabled_reason = reason.map(|reason| reason.to_string());
+        self
+    }
+
+    /// the text the query is matched against
+    pub(crate) fn search_text(&self) -> Cow<'_, str> {
+        if let Some(text) = &self.search_text {
+            return Cow::Borrowed(text);
+        }
+        if self.keywords.is_empty() {
+            return Cow::Borrowed(&self.alias);
+        }
+        let mut text = self.alias.clone();
+        for keyword in &self.keywords {
+            text.push(' ');
+            text.push_str(keyword);
+        }
+        Cow::Owned(text)
+    }
 }
 
 // constructor
-impl<T: Send + Sync> Menu<T> {
+impl<T> Menu<T> {
     /// Create a new menu instance
     ///
     /// # Example
     /// ```no_run
-    /// let mut menu = termenu::Menu::new().unwrap();
+    /// let mut menu = termenu::Menu::<i32>::new().unwrap();
     /// ```
     pub fn new() -> io::Result<Menu<T>> {
         let is_pipe = !io::stdin().is_terminal();
         let (_, row) = term::get_cursor_position(is_pipe)?;
-        let (_, rows) = crossterm::terminal::size()?;
-        Ok(Menu {
+        let (cols, rows) = crossterm::terminal::size()?;
+        Ok(Self::with_size(is_pipe, row, cols, rows))
+    }
+
+    // a menu drawn from the given row of a terminal of the given size
+    pub(crate) fn with_size(is_pipe: bool, row: u16, cols: u16, rows: u16) -> Menu<T> {
+        Menu {
             is_pipe,
             enable_print_result: true,
             colorscheme: ColorScheme::default(),
@@ -37,17 +137,54 @@ impl<T: Send + Sync> Menu<T> {
             mode: Mode::Normal,
             cursor_abs_pos: (row, 0),
             max_row: rows,
+            max_col: cols,
             selection_idx: 0,
             selected: false,
-            fuzzy_matcher: OnceCell::new(),
+            selection: None,
+            initial_cursor: None,
+            multi_select: false,
+            marked: HashSet::new(),
+            initial_marks: None,
+            tree: None,
+            expanded: HashSet::new(),
+            next_item_id: 0,
+            exit_keys: Vec::new(),
+            exit_key: None,
+            key_bindings: Vec::new(),
+            exit_code: None,
+            handle_shared: Arc::new(handle::Shared::new()),
+            status: None,
+            pending_count: None,
+            initial_query: None,
             query: String::new(),
             insert_idx: 0,
             scroll_offset: 0,
             max_height_percent: 1.0,
-            matched_item_indices: Vec::new(),
+            matches: MatchState::default(),
+            query_cache: HashMap::new(),
+            ranking: Ranking::default(),
+            haystack: None,
+            scorer: None,
+            item_scorer: None,
+            search_description: false,
+            pending_match: None,
+            match_worker: None,
             show_end_tag: true,
-            rayon_pool: OnceCell::new(),
-        })
+            pointer: "> ".to_string(),
+            marker: "+ ".to_string(),
+            prompt: " /".to_string(),
+            more_tag: "---more---".to_string(),
+            end_tag: "---end---".to_string(),
+            cycle: false,
+            enable_mouse: false,
+            color_depth: ColorDepth::detect(),
+            last_click: None,
+            parallel_matching: true,
+            parallel_threshold: query::DEFAULT_PARALLEL_THRESHOLD,
+            parallel_chunk_size: query::DEFAULT_PARALLEL_CHUNK_SIZE,
+            num_threads: None,
+            rayon_pool: None,
+        }
     }
 
     /// Set if show the '---end---' tag at the end of the menu
@@ -56,6 +193,55 @@ impl<T: Send + Sync> Menu<T> {
         self
     }
 
+    /// Set the string drawn in front of the item under the cursor, default is `"> "`. The other
+    /// items are indented by as many spaces
+    pub fn set_pointer(&mut self, pointer: &str) -> &mut Self {
+        self.pointer = pointer.to_string();
+        self
+    }
+
+    /// Set the string drawn in front of the marked items when multi-select is enabled, default is
+    /// `"+ "`. The other items are indented by as many spaces
+    pub fn set_marker(&mut self, marker: &str) -> &mut Self {
+        self.marker = marker.to_string();
+        self
+    }
+
+    /// Set the string drawn between the title and the query in query mode, default is `" /"`
+    pub fn set_prompt(&mut self, prompt: &str) -> &mut Self {
+        self.prompt = prompt.to_string();
+        self
+    }
+
+    /// Set the tag drawn below the items when there are more than the screen can display,
+    /// default is `"---more---"`
+    pub fn set_more_tag(&mut self, tag: &str) -> &mut Self {
+        self.more_tag = tag.to_string();
+        self
+    }
+
+    /// Set the tag drawn below the last item, default is `"---end---"`
+    pub fn set_end_tag(&mut self, tag: &str) -> &mut Self {
+        self.end_tag = tag.to_string();
+        self
+    }
+
+    /// Set if moving the cursor past either end of the list wraps around to the other end
+    pub fn set_cycle(&mut self, b: bool) -> &mut Self {
+        self.cycle = b;
+        self
+    }
+
+    /// Set if capture mouse events while the menu is open.
+    ///
+    /// When enabled, clicking a row moves the cursor to it, the wheel moves the cursor and
+    /// double-clicking a row selects it. Note that the terminal's own text selection does not work
+    /// while the mouse is captured.
+    pub fn enable_mouse(&mut self, b: bool) -> &mut Self {
+        self.enable_mouse = b;
+        self
+    }
+
     /// Set the max height of the menu, should be a percentage in range (0, 1], otherwise it will
     /// be ignored
     pub fn set_max_height(&mut self, percent: f32) -> &mut Self {
@@ -91,17 +277,92 @@ impl<T: Send + Sync> Menu<T> {
         self
     }
 
+    /// Set the item the cursor is on when the menu opens, by its index in the order the items
+    /// were added (children come right after their parent). The list is scrolled to show it, and
+    /// the tree is expanded if it's nested. The cursor stays on the same item if the list changes
+    /// before the menu opens.
+    ///
+    /// It only applies to the next run of the menu.
+    pub fn set_cursor(&mut self, index: usize) -> &mut Self {
+        let id = self.item_list.get(index).map(|item| item.id);
+        self.initial_cursor = Some(Box::new(move |item| Some(item.id) == id));
+        self
+    }
+
+    /// Put the cursor on the first item matching the predicate when the menu opens, see
+    /// [set_cursor](Self::set_cursor). The items are checked when the menu opens, so the ones
+    /// added after the call are checked too.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Item, Menu};
+    /// let current_branch = "dev";
+    /// let mut menu = Menu::new().unwrap();
+    /// menu.add_list(vec![Item::new("main", 1), Item::new("dev", 2)])
+    ///     .set_cursor_by(move |item| item.alias == current_branch);
+    /// ```
+    pub fn set_cursor_by<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: Fn(&Item<T>) -> bool + Send + 'static,
+    {
+        self.initial_cursor = Some(Box::new(predicate));
+        self
+    }
+
+    /// Open the menu in query mode with the query already typed, e.g. to keep the query of the
+    /// last run when the menu is opened again.
+    ///
+    /// It only applies to the next run of the menu.
+    pub fn set_query(&mut self, query: &str) -> &mut Self {
+        self.initial_query = Some(query.to_string());
+        self
+    }
+
+    /// Close the menu when the key is pressed and select the item under the cursor, whether it's
+    /// disabled or has children. [exit_key](Self::exit_key) tells which key closed the menu, so
+    /// the caller can act on the selection and open the menu again.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use termenu::{Item, KeyCode, KeyEvent, KeyModifiers, Menu};
+    ///
+    /// let delete = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+    /// let mut menu = Menu::new().unwrap();
+    /// menu.add(Item::new("file", 1)).add_exit_key(delete);
+    /// let selection = menu.select().unwrap().copied();
+    /// if menu.exit_key() == Some(delete) {
+    ///     // delete the selection and open the menu again
+    /// }
+    /// ```
+    pub fn add_exit_key(&mut self, key: KeyEvent) -> &mut Self {
+        if !self.exit_keys.contains(&key) {
+            self.exit_keys.push(key);
+        }
+        self
+    }
+
+    /// Display a message in place of the more/end tag until the next key is pressed, e.g. to
+    /// tell what happened to the selection of the last run
+    pub fn set_status(&mut self, status: &str) -> &mut Self {
+        self.status = Some(status.to_string());
+        self
+    }
+
     pub(crate) fn get_title(&self) -> &str {
         self.title.as_deref().unwrap_or("select")
     }
 
     pub fn add(&mut self, item: Item<T>) -> &mut Self {
-        self.item_list.push(item);
+        tree::flatten(item, &mut self.next_item_id, &mut self.item_list);
+        self.invalidate_query_cache();
         self
     }
 
     pub fn add_list(&mut self, items: Vec<Item<T>>) -> &mut Self {
-        self.item_list.extend(items);
+        for item in items {
+            tree::flatten(item, &mut self.next_item_id, &mut self.item_list);
+        }
+        self.invalidate_query_cache();
         self
     }
 
@@ -109,52 +370,131 @@ impl<T: Send + Sync> Menu<T> {
     /// this will not reset the color scheme and other reusable settings
     pub fn reset(&mut self) -> io::Result<()> {
         self.item_list.clear();
-        self.matched_item_indices.clear();
+        self.matches.clear();
+        self.tree = None;
+        self.expanded.clear();
+        self.invalidate_query_cache();
         self.selected = false;
+        self.selection = None;
+        self.initial_cursor = None;
+        self.initial_marks = None;
+        self.marked.clear();
         self.mode = Mode::Normal;
 
         // let (_, row) = crossterm::cursor::position()?;
         let (_, row) = term::get_cursor_position(self.is_pipe)?;
-        let (_, rows) = crossterm::terminal::size()?;
+        let (cols, rows) = crossterm::terminal::size()?;
         self.cursor_abs_pos = (row, 0);
         self.max_row = rows;
+        self.max_col = cols;
         self.query = String::new();
         self.insert_idx = 0;
+        self.selection_idx = 0;
         self.scroll_offset = 0;
+        self.pending_count = None;
+        self.status = None;
+        self.last_click = None;
 
         Ok(())
     }
 }
 
 // select api
-impl<T: Send + Sync> Menu<T> {
+impl<T> Menu<T> {
     /// Start the menu and return the selection
     /// if the user presses `esc` or `ctrl-c`, `None` will be returned
     /// otherwise, the selected item will be returned
     pub fn select(&mut self) -> io::Result<Option<&T>> {
-        match self.select0()? {
-            Some(item) => Ok(Some(&item.value)),
-            None => Ok(None),
-        }
+        let idx = self.select0()?;
+        Ok(idx.map(|idx| &self.item_list[idx].value))
     }
 
     /// Same as [select](Self::select), but return the selected [Item] instead of the value
     pub fn select_item(&mut self) -> io::Result<Option<&Item<T>>> {
-        self.select0()
+        let idx = self.select0()?;
+        Ok(idx.map(|idx| &self.item_list[idx]))
+    }
+
+    /// Same as [select_item](Self::select_item), but consume the menu and move the selected
+    /// [Item] out of it, so the result is not tied to the lifetime of the menu
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Item, Menu};
+    /// fn pick() -> Option<Item<String>> {
+    ///     let mut menu = Menu::new().unwrap();
+    ///     menu.add(Item::new("a", "a".to_string()));
+    ///     menu.select_owned().unwrap()
+    /// }
+    /// ```
+    pub fn select_owned(mut self) -> io::Result<Option<Item<T>>> {
+        self.select0()?;
+        Ok(self.take_selection())
     }
 
-    fn select0(&mut self) -> io::Result<Option<&Item<T>>> {
-        use crossterm::event;
-        if self.item_list.is_empty() {
+    /// Move the item selected by the last run out of the menu, return `None` if nothing was
+    /// selected or it has been taken already. Its children are moved out with it.
+    ///
+    /// This is useful when the menu is reused, since the borrow returned by
+    /// [select](Self::select) has to end before the menu can run again.
+    pub fn take_selection(&mut self) -> Option<Item<T>> {
+        let idx = self.selection.take()?;
+        self.matches.clear();
+        self.invalidate_query_cache();
+        let end = self.subtree_end(idx);
+        let item = tree::unflatten(self.item_list.drain(idx..end).collect());
+        self.refresh_tree();
+        Some(item)
+    }
+
+    /// The key added by [add_exit_key](Self::add_exit_key) that closed the last run, `None` if it
+    /// was closed by another key
+    pub fn exit_key(&self) -> Option<KeyEvent> {
+        self.exit_key
+    }
+
+    /// The query of the last run, `None` if it ended in normal mode
+    pub fn query(&self) -> Option<&str> {
+        match self.mode {
+            Mode::Query => Some(&self.query),
+            Mode::Normal => None,
+        }
+    }
+
+    pub(crate) fn select0(&mut self) -> io::Result<Option<usize>> {
+        self.selection = None;
+        self.apply_updates();
+        // items may be sent by the handles later
+        let has_handles = self.has_handles();
+        if self.item_list.is_empty() && !has_handles {
             return Ok(None);
         }
-        terminal::enable_raw_mode()?;
-        self.scroll_to_fit()?;
-        self.enter_normal_mode()?;
-        self.draw()?;
+        self.refresh_tree();
+        let item_cnt = match has_handles {
+            true => usize::MAX,
+            false => self.row_count(),
+        };
+        self.start(item_cnt)?;
+        let mut events = EventReader::spawn();
+        let progress = self.match_worker().signal();
+        let updates = self.handle_shared.clone();
         loop {
-            if let event::Event::Key(key) = event::read()? {
-                let resp = self.dispatch_key(key)?;
+            // keep drawing the progress of the background matching and the updates sent by the
+            // handles while waiting for events, they wake up the loop when they're ready
+            let mut cx = Context::from_waker(events.waker());
+            let mut redraw = false;
+            if updates.poll_ready(&mut cx).is_ready() {
+                redraw |= self.apply_updates();
+            }
+            if progress.poll_ready(&mut cx).is_ready() {
+                redraw |= self.receive_matches();
+            }
+            if redraw {
+                self.draw()?;
+                continue;
+            }
+            if let Wakeup::Event(evt) = events.next() {
+                let resp = self.dispatch_event(evt?)?;
                 if resp.exit() {
                     break;
                 }
@@ -163,11 +503,135 @@ impl<T: Send + Sync> Menu<T> {
                 }
             }
         }
+        drop(events);
+        self.stop()?;
         Ok(self.get_selection())
     }
 
+    // prepare the terminal and draw the menu for the first time, `item_cnt` is the number of
+    // rows to reserve for items
+    pub(crate) fn start(&mut self, item_cnt: usize) -> io::Result<()> {
+        self.selected = false;
+        self.exit_key = None;
+        self.exit_code = None;
+        // the terminal may have been used since the last run
+        self.relocate()?;
+        terminal::enable_raw_mode()?;
+        if self.enable_mouse {
+            term_exec!(crossterm::event::EnableMouseCapture);
+        }
+        self.refresh_tree();
+        self.scroll_to_fit(item_cnt)?;
+        self.enter_normal_mode()?;
+        self.apply_initial_state()?;
+        self.draw()
+    }
+
+    // mark the items, put the cursor and type the query set for this run. they're resolved
+    // now, since the item list may have changed since they were set
+    pub(crate) fn apply_initial_state(&mut self) -> io::Result<()> {
+        if let Some(predicate) = self.initial_marks.take() {
+            self.mark_matching(predicate);
+        }
+        let cursor = self
+            .initial_cursor
+            .take()
+            .and_then(|target| self.item_list.iter().position(target));
+        match self.initial_query.take() {
+            Some(query) => {
+                self.enter_query_mode()?;
+                self.insert_idx = query.chars().count();
+                self.query = query;
+                self.start_matching(cursor);
+            }
+            None => {
+                if let Some(idx) = cursor {
+                    self.move_cursor_to_item(idx)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // give the terminal back, so it can be used before the menu runs again
+    pub(crate) fn stop(&mut self) -> io::Result<()> {
+        if self.enable_mouse {
+            term_exec!(crossterm::event::DisableMouseCapture);
+        }
+        term_exec!(crossterm::cursor::Show);
+        terminal::disable_raw_mode()
+    }
+
+    pub(crate) fn dispatch_event(
+        &mut self,
+        evt: crossterm::event::Event,
+    ) -> io::Result<KeyResponse> {
+        use crossterm::event::Event;
+        match evt {
+            Event::Key(key) => {
+                // the status is only displayed until the next key
+                let had_status = self.status.take().is_some();
+                let resp = self.dispatch_key(key)?;
+                Ok(KeyResponse::new(resp.exit(), resp.redraw() || had_status))
+            }
+            Event::Mouse(evt) if self.enable_mouse => {
+                // clicks and scrolls clear the status like keys, moving the mouse doesn't
+                let had_status = match evt.kind {
+                    crossterm::event::MouseEventKind::Moved => false,
+                    _ => self.status.take().is_some(),
+                };
+                let resp = self.dispatch_mouse(evt)?;
+                Ok(KeyResponse::new(resp.exit(), resp.redraw() || had_status))
+            }
+            _ => Ok(KeyResponse::new(false, false)),
+        }
+    }
+
+    // append items while the menu is open, the active query is re-run and the cursor stays on
+    // the same item when possible
+    pub(crate) fn extend_while_open(&mut self, items: Vec<Item<T>>) -> io::Result<KeyResponse> {
+        let current = self.cursor_item_idx();
+        for item in items {
+            tree::flatten(item, &mut self.next_item_id, &mut self.item_list);
+        }
+        self.invalidate_query_cache();
+        self.refresh_tree();
+        if let Mode::Query = self.mode {
+            self.start_matching(current);
+        }
+        Ok(KeyResponse::new(false, true))
+    }
+
+    // replace all the items while the menu is open, the active query is re-run and the cursor
+    // stays on the item with the same alias when possible
+    pub(crate) fn replace_while_open(&mut self, items: Vec<Item<T>>) {
+        let current = self
+            .cursor_item_idx()
+            .map(|idx| self.item_list[idx].alias.clone());
+        self.item_list.clear();
+        for item in items {
+            tree::flatten(item, &mut self.next_item_id, &mut self.item_list);
+        }
+        self.expanded.clear();
+        self.invalidate_query_cache();
+        self.refresh_tree();
+
+        let keep = current.and_then(|alias| self.item_list.iter().position(|i| i.alias == alias));
+        self.restore_cursor(keep);
+    }
+
+    // draw the menu from where the cursor is now
+    pub(crate) fn relocate(&mut self) -> io::Result<()> {
+        let (_, row) = term::get_cursor_position(self.is_pipe)?;
+        let (cols, rows) = terminal::size()?;
+        self.cursor_abs_pos = (row, 0);
+        self.max_row = rows;
+        self.max_col = cols;
+        Ok(())
+    }
+
     // when the cursor is at the bottom of the screen, scroll up to fit the menu
-    fn scroll_to_fit(&mut self) -> io::Result<()> {
+    pub(crate) fn scroll_to_fit(&mut self, item_cnt: usize) -> io::Result<()> {
         // get the size of the terminal
         let (row, _) = self.cursor_abs_pos;
 
@@ -177,9 +641,7 @@ impl<T: Send + Sync> Menu<T> {
 
         // check how many items are there
         let item_cnt =
-            self.item_list
-                .len()
-                .min((self.max_row as f32 * self.max_height_percent) as usize) as u16;
+            item_cnt.min((self.max_row as f32 * self.max_height_percent) as usize) as u16;
 
         // if there are more rows than items, no need to scroll
         // plus 2 is for the title and the more tag
@@ -197,25 +659,24 @@ impl<T: Send + Sync> Menu<T> {
         Ok(())
     }
 
-    fn get_selection(&mut self) -> Option<&Item<T>> {
+    // clear the menu and print the result, return the index of the selected item in `item_list`
+    pub(crate) fn get_selection(&mut self) -> Option<usize> {
         ignore_io_error!(self.clear()?);
 
         if !self.selected {
             if self.enable_print_result {
             ignore_io_error!({
-                term_printf!("{}", colorize(self.get_title(), &self.colorscheme.title),);
+                term_printf!(
+                    "{}",
+                    colorize(self.get_title(), &self.colorscheme.title, self.color_depth),
+                );
                 term_cursor_down!(1);
             });
         }
             return None;
         }
 
-        let item_idx = match self.mode {
-            Mode::Normal => (self.selection_idx + self.scroll_offset) as usize,
-            Mode::Query => {
-                self.matched_item_indices[(self.selection_idx + self.scroll_offset) as usize]
-            }
-        };
+        let item_idx = self.item_idx_at(self.cursor_idx())?;
 
         let item = self.item_list.get(item_idx).unwrap();
 
@@ -224,24 +685,80 @@ impl<T: Send + Sync> Menu<T> {
             ignore_io_error!({
                 term_printf!(
                     "{} {}",
-                    colorize(self.get_title(), &self.colorscheme.title),
+                    colorize(self.get_title(), &self.colorscheme.title, self.color_depth),
                     item.alias
                 );
                 term_cursor_down!(1);
             });
         }
 
-        Some(item)
+        self.selection = Some(item_idx);
+        Some(item_idx)
     }
 }
 
 // drop trait
-impl<T: Send + Sync> Drop for Menu<T> {
+impl<T> Drop for Menu<T> {
     fn drop(&mut self) {
         ignore_io_error!({
+            if self.enable_mouse {
+                term_exec!(crossterm::event::DisableMouseCapture);
+            }
             term_cursor_col!(0);
             term_exec!(crossterm::cursor::Show);
         });
         terminal::disable_raw_mode().unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{Event, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+    use crate::{Item, Menu, Response};
+
+    fn assert_send<S: Send>(_: &S) {}
+
+    fn mouse(kind: MouseEventKind) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column: 0,
+            row: 1,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn mouse_events_clear_the_status() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(vec![Item::new("a", 1), Item::new("b", 2)])
+            .enable_mouse(true);
+
+        menu.set_status("deleted");
+        let resp = menu.dispatch_event(mouse(MouseEventKind::Moved)).unwrap();
+        assert!(!resp.redraw());
+        assert_eq!(menu.status.as_deref(), Some("deleted"));
+
+        let resp = menu.dispatch_event(mouse(MouseEventKind::ScrollDown));
+        assert!(resp.unwrap().redraw());
+        assert_eq!(menu.status, None);
+
+        // redrawn even if the click doesn't move the cursor
+        menu.set_status("deleted");
+        let resp = menu.dispatch_event(mouse(MouseEventKind::Down(MouseButton::Right)));
+        assert!(resp.unwrap().redraw());
+        assert_eq!(menu.status, None);
+    }
+
+    #[test]
+    fn menu_is_send() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add(Item::new("a", 1))
+            .set_scorer(|item: &Item<i32>, score| score + item.weight)
+            .on_key(crate::KeyEvent::from(crate::KeyCode::Enter), |_| {
+                Response::Select
+            });
+        assert_send(&menu);
+        assert_send(&menu.handle());
+    }
+}
@@ -1,7 +1,17 @@
-use crate::{color::colorize, macros::*, Menu, Mode};
+use crate::{
+    color::{colorize, colorize_line},
+    macros::*,
+    Menu, Mode,
+};
 use std::io;
 
-impl<T: Send + Sync> Menu<T> {
+// min number of spaces between an item and its description
+const DESCRIPTION_GAP: usize = 2;
+// descriptions are not drawn if there are fewer cells than this for them
+const MIN_DESCRIPTION_WIDTH: usize = 4;
+const ELLIPSIS: char = '…';
+
+impl<T> Menu<T> {
     pub(crate) fn draw(&self) -> io::Result<()> {
         self.clear()?;
 
@@ -26,12 +36,13 @@ impl<T: Send + Sync> Menu<T> {
     }
 
     pub(crate) fn print_title(&self) -> io::Result<()> {
-        let mut title = colorize(self.get_title(), &self.colorscheme.title);
+        let mut title = colorize(self.get_title(), &self.colorscheme.title, self.color_depth);
         if let Mode::Query = self.mode {
             title = format!(
-                "{} /{}",
+                "{}{}{}",
                 title,
-                colorize(&self.query, &self.colorscheme.query)
+                colorize(&self.prompt, &self.colorscheme.prompt, self.color_depth),
+                colorize(&self.query, &self.colorscheme.query, self.color_depth)
             );
         }
         term_printf!("{}", title);
@@ -48,15 +59,23 @@ impl<T: Send + Sync> Menu<T> {
 
     pub(crate) fn print_options(&self) -> io::Result<()> {
         let (row, _) = self.cursor_abs_pos;
-        let item_count = match self.mode {
-            Mode::Normal => self.item_list.len(),
-            Mode::Query => self.matched_item_indices.len(),
-        } as u16;
+        let item_count = self.item_count();
 
         // print since the scroll offset
         let mut idx = self.scroll_offset;
         let mut i = 0;
         let mut has_more = false;
+        let mut header_drawn = false;
+        // keep the last column empty, so a line never wraps
+        let line_width = (self.max_col as usize).saturating_sub(1);
+        // the pointer is drawn in front of the chosen item, the others are indented as much
+        let pointer_width = str_width(&self.pointer);
+        // followed by a column for the marker if items can be marked
+        let marker_width = match self.multi_select {
+            true => str_width(&self.marker),
+            false => 0,
+        };
+        let width = line_width.saturating_sub(pointer_width + marker_width);
         loop {
                 term_cursor_down!(1);
 
@@ -68,35 +87,245 @@ impl<T: Send + Sync> Menu<T> {
             // reach the end of the screen
             if row + i == self.max_row - 2 {
                 has_more = true;
-                term_print!(colorize("---more---", &self.colorscheme.more_tag));
                 break;
             }
 
+            // the header of a group takes a row above its first item
+            if let Some(group) = self.header_above(idx).filter(|_| !header_drawn) {
+                let name = truncate(group, line_width);
+                term_print!(colorize(&name, &self.colorscheme.header, self.color_depth));
+                header_drawn = true;
+                i += 1;
+                continue;
+            }
+
             // print
-            let item_idx = match self.mode {
-                Mode::Normal => idx as usize,
-                Mode::Query => self.matched_item_indices[idx as usize],
-            };
+            let item_idx = self.item_idx_at(idx).unwrap();
             let item = &self.item_list[item_idx];
-
-            if idx == self.selection_idx + self.scroll_offset {
-                term_printf!("> {}", colorize(&item.alias, &self.colorscheme.chosen_ln));
+            let (mut prefix, prefix_width) = self.tree_prefix(item_idx, width / 2);
+            if self.multi_select {
+                let marker = match self.is_marked(item_idx) {
+                    true => {
+                        let style = self.colorscheme.marker_style();
+                        colorize(&self.marker, style, self.color_depth)
+                    }
+                    false => " ".repeat(marker_width),
+                };
+                prefix.insert_str(0, &marker);
+            }
+            let width = width - prefix_width;
+            let (alias, alias_width, truncated) = fit(&item.alias, width);
+            let chosen = idx == self.cursor_idx();
+            let mut line = if chosen {
+                format!(
+                    "{}{}{}",
+                    colorize(&self.pointer, &self.colorscheme.pointer, self.color_depth),
+                    prefix,
+                    colorize(alias, &self.colorscheme.chosen_ln, self.color_depth)
+                )
             } else {
                 let text = match self.mode {
-                    Mode::Normal => item.get_colored_alias_for_normal_mode(&self.colorscheme),
-                    Mode::Query => item.get_colored_alias_for_query_mode(&self.colorscheme),
+                    _ if !item.enabled => {
+                        colorize(alias, &self.colorscheme.disabled, self.color_depth)
+                    }
+                    Mode::Normal => item.get_colored_alias_for_normal_mode(
+                        &self.colorscheme,
+                        self.color_depth,
+                        alias,
+                    ),
+                    Mode::Query => item.get_colored_alias_for_query_mode(
+                        &self.colorscheme,
+                        self.color_depth,
+                        alias,
+                        self.matches.indices(idx),
+                    ),
                 };
-                term_printf!("  {}", text);
+                format!("{}{}{}", " ".repeat(pointer_width), prefix, text)
+            };
+            if truncated {
+                line.push(ELLIPSIS);
+            }
+
+            // right-align the description in the space left, it's dropped if it doesn't fit
+            let mut space = width - alias_width;
+            if let Some(description) = item.description.as_ref().filter(|_| !truncated) {
+                if space >= DESCRIPTION_GAP + MIN_DESCRIPTION_WIDTH {
+                    let (shown, shown_width, truncated) = fit(description, space - DESCRIPTION_GAP);
+                    let mut description = shown.to_string();
+                    if truncated {
+                        description.push(ELLIPSIS);
+                    }
+                    line.push_str(&" ".repeat(space - shown_width));
+                    line.push_str(&colorize(
+                        &description,
+                        &self.colorscheme.description,
+                        self.color_depth,
+                    ));
+                    space = 0;
+                }
+            }
+            if chosen {
+                // the background of the chosen line fills the space left
+                line = colorize_line(&line, space, &self.colorscheme.chosen_bg, self.color_depth);
             }
+            term_print!(line);
 
             idx += 1;
             i += 1;
+            header_drawn = false;
         }
 
-        if !has_more && self.show_end_tag {
-            term_print!(colorize("---end---", &self.colorscheme.more_tag));
+        // the status takes the place of the more/end tag
+        match &self.status {
+            Some(status) => {
+                let status = truncate(status, line_width);
+                term_print!(colorize(
+                    &status,
+                    &self.colorscheme.more_tag,
+                    self.color_depth
+                ));
+            }
+            None if has_more => {
+                let tag = truncate(&self.more_tag, line_width);
+                term_print!(colorize(&tag, &self.colorscheme.more_tag, self.color_depth));
+            }
+            None if self.show_end_tag => {
+                let tag = truncate(&self.end_tag, line_width);
+                let style = self.colorscheme.end_tag_style();
+                term_print!(colorize(&tag, style, self.color_depth));
+            }
+            None => {}
         }
 
         Ok(())
     }
+
+    // what's drawn before the alias of an item in a tree, the indent guides and a marker telling
+    // if it's expanded in normal mode, the path of its ancestors in query mode.
+    // return the colored prefix and its width, which is at most `max_width`
+    fn tree_prefix(&self, idx: usize, max_width: usize) -> (String, usize) {
+        if self.tree.is_none() {
+            return (String::new(), 0);
+        }
+        let (mut prefix, mut width) = match self.mode {
+            Mode::Normal => {
+                let marker = match self.has_children(idx) {
+                    false => "  ",
+                    true if self.is_expanded(idx) => "▾ ",
+                    true => "▸ ",
+                };
+                let depth = self.item_list[idx].depth;
+                // the guides and markers take a cell each
+                let prefix = format!("{}{}", "│ ".repeat(depth), marker);
+                (prefix, depth * 2 + 2)
+            }
+            Mode::Query => {
+                let prefix: String = self
+                    .ancestor_path(idx)
+                    .iter()
+                    .map(|alias| format!("{}/", alias))
+                    .collect();
+                let width = str_width(&prefix);
+                (prefix, width)
+            }
+        };
+        if width > max_width {
+            let (shown, shown_width, _) = fit(&prefix, max_width);
+            prefix = format!("{}{}", shown, ELLIPSIS);
+            width = shown_width;
+        }
+        (
+            colorize(&prefix, &self.colorscheme.description, self.color_depth),
+            width,
+        )
+    }
+}
+
+// number of cells a char takes in the terminal
+fn char_width(c: char) -> usize {
+    // e.g. a Chinese character takes 2 cells, a combining accent takes none
+    unicode_width::UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+// number of cells a string takes in the terminal
+pub(crate) fn str_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+// the longest prefix of `s` that fits in `cells` with an ellipsis after it if it's truncated,
+// return (prefix, cells taken including the ellipsis, whether it's truncated)
+fn fit(s: &str, cells: usize) -> (&str, usize, bool) {
+    let width = str_width(s);
+    if width <= cells {
+        return (s, width, false);
+    }
+    let mut taken = 0;
+    let mut end = 0;
+    for (i, c) in s.char_indices() {
+        if taken + char_width(c) + 1 > cells {
+            break;
+        }
+        taken += char_width(c);
+        end = i + c.len_utf8();
+    }
+    (&s[..end], (taken + 1).min(cells), true)
+}
+
+// `s` cut to fit in `cells`, with an ellipsis if it's truncated
+fn truncate(s: &str, cells: usize) -> String {
+    let (shown, _, truncated) = fit(s, cells);
+    let mut s = shown.to_string();
+    if truncated {
+        s.push(ELLIPSIS);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_chars_take_two_cells() {
+        assert_eq!(str_width("菜单"), 4);
+        assert_eq!(fit("菜单", 4), ("菜单", 4, false));
+        // the second char doesn't fit with the ellipsis after it
+        assert_eq!(fit("菜单项", 4), ("菜", 3, true));
+        assert_eq!(fit("菜单项", 5), ("菜单", 5, true));
+    }
+
+    #[test]
+    fn accented_chars_take_one_cell() {
+        assert_eq!(str_width("café"), 4);
+        assert_eq!(fit("café", 4), ("café", 4, false));
+        assert_eq!(fit("cafés", 4), ("caf", 4, true));
+    }
+
+    #[test]
+    fn combining_chars_take_no_cell() {
+        // "e" followed by a combining acute accent
+        let s = "cafe\u{301}s";
+        assert_eq!(str_width(s), 5);
+        assert_eq!(fit(s, 5), (s, 5, false));
+        // the accent stays with its base char
+        assert_eq!(fit("cafe\u{301}st", 5), ("cafe\u{301}", 5, true));
+        assert_eq!(fit(s, 4), ("caf", 4, true));
+    }
+
+    #[test]
+    fn nothing_fits_in_zero_cells() {
+        assert_eq!(fit("abc", 0), ("", 0, true));
+        assert_eq!(fit("abc", 1), ("", 1, true));
+        assert_eq!(fit("", 0), ("", 0, false));
+    }
+
+    #[test]
+    fn truncated_text_ends_with_an_ellipsis() {
+        assert_eq!(truncate("菜单项", 5), format!("菜单{}", ELLIPSIS));
+        assert_eq!(truncate("cafe\u{301}s", 5), "cafe\u{301}s");
+        assert_eq!(
+            truncate("cafe\u{301}st", 5),
+            format!("cafe\u{301}{}", ELLIPSIS)
+        );
+    }
 }
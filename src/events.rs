@@ -0,0 +1,95 @@
+use std::{
+    io,
+    sync::{mpsc, Arc},
+    task::{Wake, Waker},
+    thread::{self, JoinHandle},
+};
+
+use crossterm::event::Event;
+
+/// what wakes up the event loop of the blocking select
+pub(crate) enum Wakeup {
+    // the terminal event asked for by `EventReader::next`
+    Event(io::Result<Event>),
+    // the match worker or a handle has something to apply
+    Progress,
+}
+
+struct ChannelWaker(mpsc::Sender<Wakeup>);
+
+impl Wake for ChannelWaker {
+    fn wake(self: Arc<Self>) {
+        let _ = self.0.send(Wakeup::Progress);
+    }
+}
+
+/// reads terminal events on another thread, so the event loop can wait for the events, the
+/// progress of the match worker and the updates of the handles at the same time
+pub(crate) struct EventReader {
+    requests: Option<mpsc::Sender<()>>,
+    wakeups: mpsc::Receiver<Wakeup>,
+    waker: Waker,
+    // whether an event has been asked for and not received yet
+    reading: bool,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventReader {
+    pub(crate) fn spawn() -> Self {
+        let (sender, wakeups) = mpsc::channel();
+        let (requests, pending) = mpsc::channel::<()>();
+        let events = sender.clone();
+        let thread = thread::spawn(move || {
+            // only read when asked to, so no event is taken from the terminal once the menu is
+            // closed, and the terminal is free to be queried between two events
+            while pending.recv().is_ok() {
+                if events
+                    .send(Wakeup::Event(crossterm::event::read()))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        EventReader {
+            requests: Some(requests),
+            wakeups,
+            waker: Waker::from(Arc::new(ChannelWaker(sender))),
+            reading: false,
+            thread: Some(thread),
+        }
+    }
+
+    /// wakes up `next` when it's woken, to register with the match worker and the handles
+    pub(crate) fn waker(&self) -> &Waker {
+        &self.waker
+    }
+
+    /// wait for the next terminal event or wakeup
+    pub(crate) fn next(&mut self) -> Wakeup {
+        if !self.reading {
+            if let Some(requests) = &self.requests {
+                let _ = requests.send(());
+            }
+            self.reading = true;
+        }
+        // never disconnected, the waker keeps a sender
+        let wakeup = self.wakeups.recv().unwrap();
+        if let Wakeup::Event(_) = wakeup {
+            self.reading = false;
+        }
+        wakeup
+    }
+}
+
+impl Drop for EventReader {
+    fn drop(&mut self) {
+        self.requests = None;
+        // the thread exits once the request channel is closed, unless it's still waiting for an
+        // event, which only happens when the loop stops on an error. it's detached then, and the
+        // event it reads is dropped
+        if let Some(thread) = self.thread.take().filter(|_| !self.reading) {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::Item;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+struct Usage {
+    count: u64,
+    // unix time in seconds
+    last_used: u64,
+}
+
+/// Usage counts of items keyed by their aliases, persisted to a file.
+///
+/// Items that are selected more often and more recently get a higher score, see
+/// [scorer](Self::scorer). The file is a plain text file with one `count<TAB>last used<TAB>alias`
+/// line per item.
+///
+/// # Example
+/// ```no_run
+/// use termenu::{Frecency, Item, Menu};
+///
+/// let mut frecency = Frecency::open("/tmp/termenu_history").unwrap();
+/// let mut menu = Menu::new().unwrap();
+/// menu.add(Item::new("first", 1))
+///     .add(Item::new("second", 2))
+///     .set_scorer(frecency.scorer());
+/// if let Some(item) = menu.select_item().unwrap() {
+///     frecency.record(&item.alias);
+///     frecency.save().unwrap();
+/// }
+/// ```
+pub struct Frecency {
+    path: PathBuf,
+    usage: HashMap<String, Usage>,
+}
+
+impl Frecency {
+    /// Load the usage counts from the given file, a missing file is treated as an empty one
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Frecency> {
+        let path = path.into();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        let mut usage = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let mut fields = line.splitn(3, '\t');
+            let parsed = match (fields.next(), fields.next(), fields.next()) {
+                (Some(count), Some(last_used), Some(alias)) => count
+                    .parse()
+                    .ok()
+                    .zip(last_used.parse().ok())
+                    .map(|(count, last_used)| (alias, Usage { count, last_used })),
+                _ => None,
+            };
+            let (alias, entry) = parsed.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid usage record at line {}", i + 1),
+                )
+            })?;
+            usage.insert(alias.to_string(), entry);
+        }
+        Ok(Frecency { path, usage })
+    }
+
+    /// Count one more use of the item with the given alias
+    pub fn record(&mut self, alias: &str) {
+        // a line break would corrupt the file
+        if alias.contains('\n') {
+            return;
+        }
+        let entry = self.usage.entry(alias.to_string()).or_insert(Usage {
+            count: 0,
+            last_used: 0,
+        });
+        entry.count += 1;
+        entry.last_used = now();
+    }
+
+    /// Write the usage counts back to the file
+    pub fn save(&self) -> io::Result<()> {
+        let mut content = String::new();
+        for (alias, entry) in self.usage.iter() {
+            content.push_str(&format!(
+                "{}\t{}\t{}\n",
+                entry.count, entry.last_used, alias
+            ));
+        }
+        // write a temp file next to it and move it into place, so a crash or a concurrent reader
+        // never sees a half written file
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, &self.path).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+    }
+
+    /// The frecency of the item with the given alias, 0 if it has never been used.
+    ///
+    /// Each use counts for 40 in the last hour, 20 in the last day, 10 in the last week and 5
+    /// after that.
+    pub fn score(&self, alias: &str) -> i64 {
+        match self.usage.get(alias) {
+            Some(entry) => frecency(entry, now()),
+            None => 0,
+        }
+    }
+
+    /// A scorer for [Menu::set_scorer](crate::Menu::set_scorer) that adds the frecency and the
+    /// weight of the item to its match score.
+    ///
+    /// The scorer works on a snapshot of the current usage counts.
+    pub fn scorer<T>(&self) -> impl Fn(&Item<T>, i64) -> i64 + Send + Sync + 'static {
+        let now = now();
+        let scores: HashMap<String, i64> = self
+            .usage
+            .iter()
+            .map(|(alias, entry)| (alias.clone(), frecency(entry, now)))
+            .collect();
+        move |item, score| score + item.weight + scores.get(&item.alias).copied().unwrap_or(0)
+    }
+}
+
+fn frecency(entry: &Usage, now: u64) -> i64 {
+    let factor = match now.saturating_sub(entry.last_used) {
+        age if age < HOUR => 40,
+        age if age < DAY => 20,
+        age if age < WEEK => 10,
+        _ => 5,
+    };
+    (entry.count as i64).saturating_mul(factor)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Frecency;
+
+    #[test]
+    fn saved_counts_are_loaded_back() {
+        let dir = std::env::temp_dir().join(format!("termenu_frecency_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history");
+
+        let mut frecency = Frecency::open(&path).unwrap();
+        frecency.record("first");
+        frecency.record("first");
+        frecency.record("second");
+        frecency.save().unwrap();
+
+        let loaded = Frecency::open(&path).unwrap();
+        assert!(loaded.score("first") > loaded.score("second"));
+        assert_eq!(loaded.score("second"), frecency.score("second"));
+        assert_eq!(loaded.score("third"), 0);
+        // only the file itself is left, the temp file has been moved into place
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{tree, Item, Menu};
+
+// grouped items with headers
+impl<T> Menu<T> {
+    /// Add a group of items under a header with the given name, the header is not selectable.
+    ///
+    /// Items added to an existing group are appended to the end of it. In query mode the matched
+    /// items are ranked within their groups, and a header is only displayed if some of its items
+    /// match. The name is kept in [Item::group] of the items.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Item, Menu};
+    /// let mut menu = Menu::new().unwrap();
+    /// menu.add_group("Recent", vec![Item::new("notes.md", 1)])
+    ///     .add_group("Projects", vec![Item::new("termenu", 2), Item::new("dotfiles", 3)]);
+    /// if let Some(item) = menu.select_item().unwrap() {
+    ///     println!("{} from {:?}", item.alias, item.group);
+    /// }
+    /// ```
+    pub fn add_group(&mut self, name: &str, items: Vec<Item<T>>) -> &mut Self {
+        let at = self
+            .item_list
+            .iter()
+            .rposition(|item| item.group.as_deref() == Some(name))
+            .map_or(self.item_list.len(), |last| last + 1);
+        let mut flattened = Vec::new();
+        for item in items {
+            tree::flatten(item, &mut self.next_item_id, &mut flattened);
+        }
+        for item in flattened.iter_mut() {
+            item.group = Some(name.to_string());
+        }
+        self.item_list.splice(at..at, flattened);
+        self.invalidate_query_cache();
+        self
+    }
+
+    /// the name of the group whose header is drawn right above the item at the given index of
+    /// the current mode's list, if any
+    pub(crate) fn header_above(&self, idx: usize) -> Option<&str> {
+        let group = self.item_at(idx)?.group.as_deref()?;
+        if idx > 0 && self.item_at(idx - 1)?.group.as_deref() == Some(group) {
+            return None;
+        }
+        Some(group)
+    }
+
+    /// number of rows taken by the items from `from` to `to` (inclusive), headers included
+    pub(crate) fn rows_between(&self, from: usize, to: usize) -> usize {
+        (from..=to)
+            .map(|idx| 1 + self.header_above(idx).is_some() as usize)
+            .sum()
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{Item, Menu, Mode};
+
+type Predicate<T> = dyn FnMut(&Item<T>) -> bool + Send;
+
+// a change requested by a handle, applied by the event loop of the menu
+enum Update<T> {
+    Replace(Vec<Item<T>>),
+    Append(Vec<Item<T>>),
+    Remove(Box<Predicate<T>>),
+    Title(String),
+    Status(Option<String>),
+}
+
+pub(crate) struct Shared<T> {
+    // updates in the order they were sent
+    updates: Mutex<Vec<Update<T>>>,
+    // waker of the async event loop waiting for updates
+    waker: Mutex<Option<Waker>>,
+}
+
+impl<T> Shared<T> {
+    pub(crate) fn new() -> Self {
+        Shared {
+            updates: Mutex::new(Vec::new()),
+            waker: Mutex::new(None),
+        }
+    }
+
+    fn send(&self, update: Update<T>) {
+        self.updates.lock().unwrap().push(update);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        // register first, so updates sent after the check below still wake us up
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+        if !self.updates.lock().unwrap().is_empty() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+/// changes the items and the text of a menu from another thread, see [Menu::handle]
+///
+/// Changes made while the menu is open are applied right away: the menu is redrawn, the query is
+/// re-run and the cursor stays on the same item when possible. Changes made while it's closed
+/// are applied when it opens.
+pub struct MenuHandle<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for MenuHandle<T> {
+    fn clone(&self) -> Self {
+        MenuHandle {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> MenuHandle<T> {
+    /// Replace all the items of the menu, the cursor stays on the item with the same alias if
+    /// there is one
+    pub fn replace_items(&self, items: Vec<Item<T>>) {
+        self.shared.send(Update::Replace(items));
+    }
+
+    /// Add items to the end of the menu
+    pub fn append_items(&self, items: Vec<Item<T>>) {
+        self.shared.send(Update::Append(items));
+    }
+
+    /// Remove the items matching the predicate, along with their children
+    pub fn remove_items<F>(&self, predicate: F)
+    where
+        F: FnMut(&Item<T>) -> bool + Send + 'static,
+    {
+        self.shared.send(Update::Remove(Box::new(predicate)));
+    }
+
+    /// Set the title of the menu
+    pub fn set_title(&self, title: &str) {
+        self.shared.send(Update::Title(title.to_string()));
+    }
+
+    /// Display a message in place of the more/end tag, until the next key is pressed
+    pub fn set_status(&self, status: &str) {
+        self.shared.send(Update::Status(Some(status.to_string())));
+    }
+
+    /// Remove the message set by [set_status](Self::set_status)
+    pub fn clear_status(&self) {
+        self.shared.send(Update::Status(None));
+    }
+}
+
+// handles
+impl<T> Menu<T> {
+    /// Get a handle to change the menu from another thread while it's open.
+    ///
+    /// A menu with handles opens even if it has no items yet, since they may be sent later.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use termenu::{Item, Menu};
+    ///
+    /// let mut menu = Menu::new().unwrap();
+    /// let handle = menu.handle();
+    /// std::thread::spawn(move || {
+    ///     for i in 1..=10 {
+    ///         handle.append_items(vec![Item::new(&format!("{i}th item"), i)]);
+    ///         handle.set_status(&format!("loaded {i} items"));
+    ///         std::thread::sleep(std::time::Duration::from_millis(100));
+    ///     }
+    ///     handle.clear_status();
+    /// });
+    /// let selection = menu.select().unwrap();
+    /// ```
+    pub fn handle(&self) -> MenuHandle<T> {
+        MenuHandle {
+            shared: self.handle_shared.clone(),
+        }
+    }
+
+    /// whether some handle may still send updates
+    pub(crate) fn has_handles(&self) -> bool {
+        Arc::strong_count(&self.handle_shared) > 1
+    }
+
+    /// apply the updates sent by the handles, return true if a redraw is needed
+    pub(crate) fn apply_updates(&mut self) -> bool {
+        let updates = std::mem::take(&mut *self.handle_shared.updates.lock().unwrap());
+        let redraw = !updates.is_empty();
+        for update in updates {
+            match update {
+                Update::Replace(items) => self.replace_while_open(items),
+                Update::Append(items) => {
+                    let _ = self.extend_while_open(items);
+                }
+                Update::Remove(mut predicate) => self.remove_while_open(|_, item| predicate(item)),
+                Update::Title(title) => self.title = Some(title),
+                Update::Status(status) => self.status = status,
+            }
+        }
+        redraw
+    }
+
+    // remove the matching items and their descendants while the menu is open, the active query
+    // is re-run and the cursor stays on the same item when possible
+    pub(crate) fn remove_while_open<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(usize, &Item<T>) -> bool,
+    {
+        let current = self.cursor_item_idx();
+
+        // new index of each item, `None` if it's removed
+        let mut new_idx = Vec::with_capacity(self.item_list.len());
+        let mut kept = Vec::with_capacity(self.item_list.len());
+        let mut removed_depth = None;
+        for (idx, item) in std::mem::take(&mut self.item_list).into_iter().enumerate() {
+            match removed_depth {
+                Some(depth) if item.depth > depth => {
+                    new_idx.push(None);
+                    continue;
+                }
+                _ => removed_depth = None,
+            }
+            if predicate(idx, &item) {
+                removed_depth = Some(item.depth);
+                new_idx.push(None);
+                continue;
+            }
+            new_idx.push(Some(kept.len()));
+            kept.push(item);
+        }
+        self.item_list = kept;
+        self.invalidate_query_cache();
+        self.refresh_tree();
+
+        let keep = current.and_then(|idx| new_idx[idx]);
+        self.restore_cursor(keep);
+    }
+
+    // re-run the active query or move the cursor back after the item list changed, `keep` is
+    // the index of the item the cursor should stay on
+    pub(crate) fn restore_cursor(&mut self, keep: Option<usize>) {
+        match self.mode {
+            Mode::Query => {
+                // the matches refer to the old list until the query is matched again
+                self.matches.clear();
+                self.start_matching(keep);
+            }
+            Mode::Normal => {
+                let _ = match keep {
+                    Some(idx) => self.move_cursor_to_item(idx),
+                    None => self.move_to(self.cursor_idx()),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Item, Menu};
+
+    fn current(menu: &Menu<usize>) -> usize {
+        menu.item_at(menu.cursor_idx()).unwrap().value
+    }
+
+    fn numbered(values: impl Iterator<Item = usize>) -> Vec<Item<usize>> {
+        values.map(|i| Item::new(&i.to_string(), i)).collect()
+    }
+
+    #[test]
+    fn updates_keep_the_cursor_on_the_same_item() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(numbered(1..=5));
+        menu.move_to(2).unwrap();
+        let handle = menu.handle();
+
+        handle.remove_items(|item| item.value < 3);
+        assert!(menu.apply_updates());
+        assert_eq!(current(&menu), 3);
+
+        handle.append_items(numbered(6..=8));
+        menu.apply_updates();
+        assert_eq!(current(&menu), 3);
+
+        // by alias when all the items are replaced
+        handle.replace_items(numbered((0..=9).rev()));
+        menu.apply_updates();
+        assert_eq!(current(&menu), 3);
+        assert_eq!(menu.cursor_idx(), 6);
+
+        // on the closest item if it's removed
+        handle.remove_items(|item| item.value == 3);
+        menu.apply_updates();
+        assert_eq!(current(&menu), 2);
+    }
+
+    #[test]
+    fn updates_keep_the_cursor_on_the_same_match() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(numbered(1..=30));
+        menu.enter_query_mode().unwrap();
+        menu.query = "1".to_string();
+        let wait = |menu: &mut Menu<usize>| {
+            while menu.is_matching() {
+                menu.receive_matches();
+                std::thread::yield_now();
+            }
+        };
+        menu.fuzzy_match();
+        wait(&mut menu);
+        menu.move_to(3).unwrap();
+        let chosen = current(&menu);
+
+        let handle = menu.handle();
+        handle.remove_items(move |item| item.value != chosen && item.value % 2 == 0);
+        handle.append_items(numbered(100..=110));
+        menu.apply_updates();
+        wait(&mut menu);
+        assert_eq!(current(&menu), chosen);
+    }
+}
@@ -2,12 +2,12 @@ use std::{cmp, io};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crate::{macros::*, Menu, Mode};
+use crate::{draw::str_width, macros::*, Item, Menu, Mode};
 
 pub(crate) struct KeyResponse(bool, bool);
 
 impl KeyResponse {
-    fn new(exit: bool, redraw: bool) -> Self {
+    pub(crate) fn new(exit: bool, redraw: bool) -> Self {
         KeyResponse(exit, redraw)
     }
 
@@ -20,8 +20,17 @@ impl KeyResponse {
     }
 }
 
-impl<T: Send + Sync> Menu<T> {
+impl<T> Menu<T> {
     pub(crate) fn dispatch_key(&mut self, key: KeyEvent) -> io::Result<KeyResponse> {
+        if let Some(resp) = self.dispatch_binding(key)? {
+            return Ok(resp);
+        }
+        let pressed = normalize(key);
+        if let Some(&exit_key) = self.exit_keys.iter().find(|k| normalize(**k) == pressed) {
+            self.exit_key = Some(exit_key);
+            self.selected = self.item_idx_at(self.cursor_idx()).is_some();
+            return Ok(KeyResponse::new(true, false));
+        }
         match key.modifiers {
             KeyModifiers::NONE | KeyModifiers::SHIFT => self.dispatch_code(key.code),
 
@@ -29,9 +38,12 @@ impl<T: Send + Sync> Menu<T> {
                 if key.code == KeyCode::Char('c') {
                     return Ok(KeyResponse::new(true, false));
                 }
+                let count = self.take_count();
                 match key.code {
-                    KeyCode::Char('n') => return self.key_down(),
-                    KeyCode::Char('p') => return self.key_up(),
+                    KeyCode::Char('n') => return self.key_down(count),
+                    KeyCode::Char('p') => return self.key_up(count),
+                    KeyCode::Char('d') => return self.key_half_page_down(count),
+                    KeyCode::Char('u') => return self.key_half_page_up(count),
                     _ => {}
                 }
                 Ok(KeyResponse::new(false, false))
@@ -42,6 +54,11 @@ impl<T: Send + Sync> Menu<T> {
     }
 
     fn dispatch_code(&mut self, code: KeyCode) -> io::Result<KeyResponse> {
+        match code {
+            KeyCode::Tab if self.multi_select => return self.key_toggle_mark(true),
+            KeyCode::BackTab if self.multi_select => return self.key_toggle_mark(false),
+            _ => {}
+        }
         match self.mode {
             Mode::Normal => self.dispatch_normal(code),
             Mode::Query => self.dispatch_query(code),
@@ -49,15 +66,47 @@ impl<T: Send + Sync> Menu<T> {
     }
 
     fn dispatch_normal(&mut self, code: KeyCode) -> io::Result<KeyResponse> {
+        // count prefix, e.g. the `5` in `5j`
+        if let KeyCode::Char(c @ '0'..='9') = code {
+            if c != '0' || self.pending_count.is_some() {
+                let digit = c.to_digit(10).unwrap() as usize;
+                let count = self.pending_count.unwrap_or(0);
+                self.pending_count = Some(count.saturating_mul(10).saturating_add(digit));
+                return Ok(KeyResponse::new(false, false));
+            }
+        }
+
+        let explicit_count = self.pending_count;
+        let count = self.take_count();
         match code {
-            KeyCode::Up | KeyCode::Char('k') => return self.key_up(),
+            KeyCode::Up | KeyCode::Char('k') => return self.key_up(count),
 
-            KeyCode::Down | KeyCode::Char('j') => return self.key_down(),
+            KeyCode::Down | KeyCode::Char('j') => return self.key_down(count),
+
+            KeyCode::PageUp => return self.key_page_up(count),
+
+            KeyCode::PageDown => return self.key_page_down(count),
+
+            KeyCode::Home => return self.move_to(0),
+
+            KeyCode::End => return self.move_to(usize::MAX),
+
+            // `g` goes to the top, `G` goes to the bottom, `{count}g`/`{count}G` goes to the
+            // `count`th item
+            KeyCode::Char('g') => return self.move_to(count - 1),
+
+            KeyCode::Char('G') => {
+                return self.move_to(explicit_count.map_or(usize::MAX, |n| n.saturating_sub(1)))
+            }
 
             KeyCode::Esc => return self.key_esc(),
 
             KeyCode::Enter => return self.key_enter(),
 
+            KeyCode::Right | KeyCode::Char('l') => return self.key_expand(),
+
+            KeyCode::Left | KeyCode::Char('h') => return self.key_collapse(),
+
             KeyCode::Char('/') => {
                 self.enter_query_mode()?;
             }
@@ -70,9 +119,21 @@ impl<T: Send + Sync> Menu<T> {
         match code {
             KeyCode::Esc => return self.key_esc(),
 
-            KeyCode::Up => return self.key_up(),
+            KeyCode::Up => return self.key_up(1),
+
+            KeyCode::Down => return self.key_down(1),
+
+            KeyCode::PageUp => return self.key_page_up(1),
+
+            KeyCode::PageDown => return self.key_page_down(1),
+
+            KeyCode::Home => {
+                self.insert_idx = 0;
+            }
 
-            KeyCode::Down => return self.key_down(),
+            KeyCode::End => {
+                self.insert_idx = self.query.chars().count();
+            }
 
             KeyCode::Left => {
                 self.insert_idx = self.insert_idx.saturating_sub(1);
@@ -91,13 +152,11 @@ impl<T: Send + Sync> Menu<T> {
                 self.fuzzy_match();
             }
 
-            KeyCode::Backspace => {
-                if !self.query.is_empty() {
-                    self.insert_idx = self.insert_idx.saturating_sub(1);
-                    let pos = get_insert_pos!(&self.query, self.insert_idx);
-                    self.query.remove(pos);
-                    self.fuzzy_match();
-                }
+            KeyCode::Backspace if !self.query.is_empty() => {
+                self.insert_idx = self.insert_idx.saturating_sub(1);
+                let pos = get_insert_pos!(&self.query, self.insert_idx);
+                self.query.remove(pos);
+                self.fuzzy_match();
             }
             _ => {}
         }
@@ -105,34 +164,105 @@ impl<T: Send + Sync> Menu<T> {
     }
 }
 
-impl<T: Send + Sync> Menu<T> {
-    fn key_up(&mut self) -> io::Result<KeyResponse> {
-        if self.selection_idx == 0 {
-            if self.scroll_offset == 0 {
-                return Ok(KeyResponse(false, false));
-            }
-            self.scroll_offset -= 1;
-            return Ok(KeyResponse(false, true));
+impl<T> Menu<T> {
+    pub(crate) fn key_up(&mut self, n: usize) -> io::Result<KeyResponse> {
+        let (idx, item_cnt) = (self.cursor_idx(), self.item_count());
+        if self.cycle && item_cnt > 0 && n > idx {
+            return self.move_to((idx + item_cnt - n % item_cnt) % item_cnt);
         }
-        self.selection_idx -= 1;
-        Ok(KeyResponse(false, true))
+        self.move_to(idx.saturating_sub(n))
     }
 
-    fn key_down(&mut self) -> io::Result<KeyResponse> {
-        let item_cnt = match self.mode {
-            Mode::Normal => self.item_list.len(),
-            Mode::Query => self.matched_item_indices.len(),
-        } as u16;
-        if self.selection_idx + self.scroll_offset == item_cnt - 1 {
+    pub(crate) fn key_down(&mut self, n: usize) -> io::Result<KeyResponse> {
+        let (idx, item_cnt) = (self.cursor_idx(), self.item_count());
+        if self.cycle && item_cnt > 0 && n >= item_cnt - idx {
+            return self.move_to((idx + n % item_cnt) % item_cnt);
+        }
+        self.move_to(idx.saturating_add(n))
+    }
+
+    fn key_page_up(&mut self, n: usize) -> io::Result<KeyResponse> {
+        let step = self.visible_height().saturating_mul(n);
+        self.move_to(self.cursor_idx().saturating_sub(step))
+    }
+
+    fn key_page_down(&mut self, n: usize) -> io::Result<KeyResponse> {
+        let step = self.visible_height().saturating_mul(n);
+        self.move_to(self.cursor_idx().saturating_add(step))
+    }
+
+    fn key_half_page_up(&mut self, n: usize) -> io::Result<KeyResponse> {
+        let step = (self.visible_height() / 2).max(1).saturating_mul(n);
+        self.move_to(self.cursor_idx().saturating_sub(step))
+    }
+
+    fn key_half_page_down(&mut self, n: usize) -> io::Result<KeyResponse> {
+        let step = (self.visible_height() / 2).max(1).saturating_mul(n);
+        self.move_to(self.cursor_idx().saturating_add(step))
+    }
+
+    /// move the cursor to the given item index (clamped to the list), scrolling as little as
+    /// possible to keep it visible
+    pub(crate) fn move_to(&mut self, target: usize) -> io::Result<KeyResponse> {
+        let item_cnt = self.item_count();
+        if item_cnt == 0 {
             return Ok(KeyResponse(false, false));
         }
-        let (row, _) = self.cursor_abs_pos;
-        if self.selection_idx + row == self.max_row - 3 {
+        let forward = target >= self.cursor_idx();
+        let target = match self.nearest_enabled(target.min(item_cnt - 1), forward) {
+            Some(target) => target,
+            None => return Ok(KeyResponse(false, false)),
+        };
+        let height = self.visible_height();
+        let before = (self.selection_idx, self.scroll_offset);
+
+        if target < self.scroll_offset {
+            self.scroll_offset = target;
+        } else if target >= self.scroll_offset + height {
+            self.scroll_offset = target + 1 - height;
+        }
+        // group headers take rows too, scroll a bit more if they push the target out of sight
+        self.ensure_ranked(target + 1);
+        while self.scroll_offset < target && self.rows_between(self.scroll_offset, target) > height
+        {
             self.scroll_offset += 1;
-            return Ok(KeyResponse(false, true));
         }
-        self.selection_idx += 1;
-        Ok(KeyResponse(false, true))
+        self.selection_idx = target - self.scroll_offset;
+        self.ensure_ranked(self.scroll_offset + height);
+
+        Ok(KeyResponse(
+            false,
+            before != (self.selection_idx, self.scroll_offset),
+        ))
+    }
+
+    /// move the cursor to the item at the given index of `item_list` in normal mode, revealing
+    /// it if it's hidden in the tree
+    pub(crate) fn move_cursor_to_item(&mut self, idx: usize) -> io::Result<KeyResponse> {
+        if idx >= self.item_list.len() {
+            return Ok(KeyResponse(false, false));
+        }
+        self.reveal(idx);
+        match self.row_of(idx) {
+            Some(row) => self.move_to(row),
+            None => Ok(KeyResponse(false, false)),
+        }
+    }
+
+    // the closest enabled item from `target` in the given direction, or in the other direction
+    // if there is none
+    fn nearest_enabled(&mut self, target: usize, forward: bool) -> Option<usize> {
+        let is_enabled = |menu: &mut Self, idx: usize| {
+            menu.ensure_ranked(idx + 1);
+            menu.item_at(idx).is_some_and(|item| item.enabled)
+        };
+        let below = |menu: &mut Self| (target..menu.item_count()).find(|&i| is_enabled(menu, i));
+        let above = |menu: &mut Self| (0..=target).rev().find(|&i| is_enabled(menu, i));
+        if forward {
+            below(self).or_else(|| above(self))
+        } else {
+            above(self).or_else(|| below(self))
+        }
     }
 
     fn key_esc(&mut self) -> io::Result<KeyResponse> {
@@ -145,39 +275,217 @@ impl<T: Send + Sync> Menu<T> {
         }
     }
 
-    fn key_enter(&mut self) -> io::Result<KeyResponse> {
-        match self.mode {
-            Mode::Normal => {
-                self.selected = true;
-            }
-            Mode::Query => {
-                if !self.matched_item_indices.is_empty() {
-                    self.selected = true;
-                }
-            }
+    pub(crate) fn key_enter(&mut self) -> io::Result<KeyResponse> {
+        let idx = match self.item_idx_at(self.cursor_idx()) {
+            Some(idx) => idx,
+            None => return Ok(KeyResponse(true, false)),
+        };
+        // only leaves can be selected in a tree
+        if self.has_children(idx) {
+            return self.key_enter_branch(idx);
         }
+        let item = &self.item_list[idx];
+        if !item.enabled {
+            let reason = item.disabled_reason.as_deref().unwrap_or("disabled");
+            self.status = Some(format!("{}: {}", item.alias, reason));
+            return Ok(KeyResponse(false, true));
+        }
+        self.selected = true;
         Ok(KeyResponse(true, false))
     }
 }
 
-impl<T: Send + Sync> Menu<T> {
-    pub(crate) fn get_query_cursor_col(&self) -> u16 {
-        let mut col = 0;
+impl<T> Menu<T> {
+    /// number of items that can be navigated in the current mode
+    pub(crate) fn item_count(&self) -> usize {
+        match self.mode {
+            Mode::Normal => self.row_count(),
+            Mode::Query => self.matches.len(),
+        }
+    }
+
+    /// index in `item_list` of the item at the given index of the current mode's list
+    pub(crate) fn item_idx_at(&self, idx: usize) -> Option<usize> {
+        match (&self.mode, &self.tree) {
+            (Mode::Normal, Some(tree)) => tree.rows.get(idx).copied(),
+            (Mode::Normal, None) => (idx < self.item_list.len()).then_some(idx),
+            (Mode::Query, _) => self.matches.item_idx(idx),
+        }
+    }
+
+    /// the item at the given index of the current mode's list
+    pub(crate) fn item_at(&self, idx: usize) -> Option<&Item<T>> {
+        self.item_list.get(self.item_idx_at(idx)?)
+    }
 
+    /// index of the item under the cursor, relative to the current mode's list
+    pub(crate) fn cursor_idx(&self) -> usize {
+        self.selection_idx + self.scroll_offset
+    }
+
+    /// how many items fit in the window, the title and the more tag take one row each
+    pub(crate) fn visible_height(&self) -> usize {
+        let (row, _) = self.cursor_abs_pos;
+        (self.max_row.saturating_sub(row + 2) as usize).max(1)
+    }
+
+    /// consume the pending count prefix, defaults to 1
+    fn take_count(&mut self) -> usize {
+        self.pending_count.take().unwrap_or(1).max(1)
+    }
+
+    pub(crate) fn get_query_cursor_col(&self) -> u16 {
         // calculate the prefix cells
-        let prefix = format!("{} /", self.get_title());
-        col += prefix
-            .chars()
-            // a Chinese character takes 3 bytes, however, it only takes 2 cells in terminals
-            .fold(0, |acc, c| acc + cmp::min(2, c.len_utf8() as u16));
+        let prefix = format!("{}{}", self.get_title(), self.prompt);
 
         // calculate the query cells from 0 to self.insert_idx
-        col += self
-            .query
-            .chars()
-            .take(self.insert_idx)
-            .fold(0, |acc, c| acc + cmp::min(2, c.len_utf8() as u16));
+        let typed: String = self.query.chars().take(self.insert_idx).collect();
+
+        (str_width(&prefix) + str_width(&typed)) as u16
+    }
+}
+
+// shifted chars are reported with the shift modifier by some terminals and without it by others
+pub(crate) fn normalize(key: KeyEvent) -> KeyEvent {
+    let mut modifiers = key.modifiers;
+    if let KeyCode::Char(_) = key.code {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    KeyEvent::new(key.code, modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::{Item, Menu};
+
+    fn press(menu: &mut Menu<usize>, keys: &str) {
+        for c in keys.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            menu.dispatch_key(key).unwrap();
+        }
+    }
+
+    fn press_with(menu: &mut Menu<usize>, code: KeyCode, modifiers: KeyModifiers) {
+        menu.dispatch_key(KeyEvent::new(code, modifiers)).unwrap();
+    }
+
+    fn current(menu: &Menu<usize>) -> usize {
+        menu.item_at(menu.cursor_idx()).unwrap().value
+    }
+
+    // 22 rows are visible in a 24-row terminal, so half a page is 11 items
+    fn numbered_menu(cnt: usize) -> Menu<usize> {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list((1..=cnt).map(|i| Item::new(&i.to_string(), i)).collect());
+        menu
+    }
+
+    #[test]
+    fn move_up_skips_disabled_items() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(
+            (1..=10)
+                .map(|i| match i {
+                    4 => Item::new(&i.to_string(), i).disabled(None),
+                    _ => Item::new(&i.to_string(), i),
+                })
+                .collect(),
+        );
+
+        press(&mut menu, "jjk");
+        assert_eq!(current(&menu), 2);
+
+        press(&mut menu, "jj");
+        assert_eq!(current(&menu), 5);
+        press(&mut menu, "k");
+        assert_eq!(current(&menu), 3);
+
+        press(&mut menu, "Gk");
+        assert_eq!(current(&menu), 9);
+
+        press(&mut menu, "5G");
+        assert_eq!(current(&menu), 5);
+        press(&mut menu, "2k");
+        assert_eq!(current(&menu), 3);
+    }
+
+    #[test]
+    fn jump_to_top_and_bottom() {
+        let mut menu = numbered_menu(100);
+        press(&mut menu, "G");
+        assert_eq!(current(&menu), 100);
+        press(&mut menu, "g");
+        assert_eq!(current(&menu), 1);
+
+        press_with(&mut menu, KeyCode::End, KeyModifiers::NONE);
+        assert_eq!(current(&menu), 100);
+        press_with(&mut menu, KeyCode::Home, KeyModifiers::NONE);
+        assert_eq!(current(&menu), 1);
+
+        press(&mut menu, "42G");
+        assert_eq!(current(&menu), 42);
+        press(&mut menu, "7g");
+        assert_eq!(current(&menu), 7);
+        // a count past the end goes to the last item
+        press(&mut menu, "1000G");
+        assert_eq!(current(&menu), 100);
+        // a leading `0` is not a count
+        press(&mut menu, "0g");
+        assert_eq!(current(&menu), 1);
+    }
+
+    #[test]
+    fn half_page_stops_at_the_edges() {
+        let mut menu = numbered_menu(100);
+        press_with(&mut menu, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 1);
+        press_with(&mut menu, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 12);
+        press_with(&mut menu, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 1);
+
+        press(&mut menu, "G");
+        press_with(&mut menu, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 100);
+        press_with(&mut menu, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 89);
+
+        // fewer items than half a page
+        let mut menu = numbered_menu(5);
+        press_with(&mut menu, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 5);
+        press(&mut menu, "3");
+        press_with(&mut menu, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 1);
+    }
+
+    #[test]
+    fn huge_counts_saturate() {
+        let huge = "99999999999999999999999";
+        let mut menu = numbered_menu(100);
+        press(&mut menu, &format!("{}j", huge));
+        assert_eq!(current(&menu), 100);
+        press(&mut menu, &format!("{}k", huge));
+        assert_eq!(current(&menu), 1);
+
+        press(&mut menu, huge);
+        press_with(&mut menu, KeyCode::PageDown, KeyModifiers::NONE);
+        assert_eq!(current(&menu), 100);
+        press(&mut menu, huge);
+        press_with(&mut menu, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(current(&menu), 1);
+
+        press(&mut menu, &format!("{}G", huge));
+        assert_eq!(current(&menu), 100);
 
-        col
+        // the count saturates at usize::MAX, which wraps around the list
+        menu.set_cycle(true);
+        press(&mut menu, "g");
+        press(&mut menu, &format!("{}j", huge));
+        assert_eq!(current(&menu), usize::MAX % 100 + 1);
+        press(&mut menu, &format!("{}k", huge));
+        assert_eq!(current(&menu), 1);
     }
 }
@@ -7,16 +7,33 @@
 //! ![demo](https://github.com/sshelll/assets/blob/master/termenu/termenu.jpg?raw=true)
 //!
 //! ## Key Mapping
-//! - `j`/`k` or `down`/`up` to move the cursor
+//! - `j`/`k` or `down`/`up` to move the cursor, prefix with a count like `5j` to move faster
+//! - `pageup`/`pagedown` to move the cursor by a page
+//! - `ctrl-u`/`ctrl-d` to move the cursor by half a page
+//! - `g`/`G` or `home`/`end` to jump to the top/bottom, `{count}G` jumps to the `count`th item
 //! - `enter` to select the item
+//! - `tab`/`shift-tab` to mark the item and move down/up, if
+//!   [multi-select](Menu::enable_multi_select) is enabled
 //! - `/` to enter query mode just like vim
 //! - `ctrl-n`/`ctrl-p` to move the cursor in query mode
 //! - `esc` to exit query mode or the menu
 //! - `ctrl-c` to exit the menu
 //!
+//! With [`Menu::enable_mouse`], clicking a row moves the cursor to it, the wheel moves the
+//! cursor and double-clicking a row selects it.
+//!
+//! Keys added with [`Menu::add_exit_key`] close the menu like `enter`, and
+//! [`Menu::exit_key`] tells which one did, so the caller can act on it and open the menu again.
+//! Any key can also be bound to a callback with [`Menu::on_key`], which replaces what it does by
+//! default and runs while the menu stays open.
+//!
+//! Items, the title and a status message can be changed from other threads while the menu is
+//! open through a [`MenuHandle`], see [`Menu::handle`].
+//!
 //! ## Examples
 //!
 //! ```no_run
+//! # use termenu::Item;
 //! let mut menu = termenu::Menu::new().unwrap();
 //! let mut item_list = Vec::new();
 //! for i in 1..=10 {
@@ -33,19 +50,40 @@
 //! ```
 //!
 //! Check the examples folder for more details.
+//!
+//! ## Crate Features
+//! - `async`: adds [`Menu::select_async`] and [`Menu::select_item_async`], which read terminal
+//!   events from an async stream and can be fed with items while the menu is open
 
 #![allow(dead_code)]
 
-use colored::Color;
-use fuzzy_matcher::skim::SkimMatcherV2;
-use once_cell::sync::OnceCell;
+#[cfg(feature = "async")]
+mod async_select;
+mod binding;
 mod color;
 mod core;
 mod draw;
+mod events;
+mod frecency;
+mod group;
+mod handle;
 mod keymap;
+mod mark;
 mod mode;
+mod mouse;
 mod query;
 mod term;
+mod tree;
+mod worker;
+
+pub use binding::{MenuContext, Response};
+pub use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+pub use frecency::Frecency;
+pub use handle::MenuHandle;
+
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use query::{bench_match, bench_rank};
 
 #[allow(unused_macros)]
 #[macro_use]
@@ -54,14 +92,32 @@ mod macros;
 /// item in the menu
 ///
 /// You can only store the same type of value in the menu
-pub struct Item<T>
-where
-    T: Send + Sync,
-{
+#[derive(Clone)]
+pub struct Item<T> {
     pub alias: String,
     pub value: T,
-    pub(crate) score: Option<i64>,
-    pub(crate) matched_indices: Option<Vec<usize>>,
+    /// added to the match score in query mode, so items with a greater weight rank higher.
+    /// Default is `0`
+    pub weight: i64,
+    /// text matched against the query instead of the alias and the keywords. Matched chars are
+    /// highlighted on the alias only if the alias is part of it
+    pub search_text: Option<String>,
+    /// words matched against the query after the alias but not displayed, e.g. tags or synonyms
+    pub keywords: Vec<String>,
+    /// secondary text drawn dimmed at the right of the alias
+    pub description: Option<String>,
+    /// name of the group the item was added to by [Menu::add_group]
+    pub group: Option<String>,
+    /// disabled items are displayed but can't be selected, the cursor skips them.
+    /// Default is `true`
+    pub enabled: bool,
+    /// why the item is disabled, displayed when trying to select it
+    pub disabled_reason: Option<String>,
+    // set by `with_children`, moved into the item list right after the item when it's added
+    pub(crate) children: Vec<Item<T>>,
+    pub(crate) depth: usize,
+    // identifies the item in the menu whatever its index, set when it's added
+    pub(crate) id: u64,
 }
 
 enum Mode {
@@ -70,10 +126,7 @@ enum Mode {
 }
 
 /// the menu itself
-pub struct Menu<T>
-where
-    T: Send + Sync,
-{
+pub struct Menu<T> {
     is_pipe: bool,
 
     enable_print_result: bool,
@@ -90,22 +143,115 @@ where
     // original cursor absolute position (row, col)
     cursor_abs_pos: (u16, u16),
     max_row: u16,
+    // width of the terminal
+    max_col: u16,
 
-    selection_idx: u16,
+    // index of the cursor relative to the first visible item
+    selection_idx: usize,
     selected: bool,
 
+    // finds the item to put the cursor on when the menu opens, only for the next run
+    initial_cursor: Option<Box<mark::ItemPredicate<T>>>,
+
+    // items can be marked, see `enable_multi_select`
+    multi_select: bool,
+    // ids of the marked items
+    marked: std::collections::HashSet<u64>,
+    // finds the items to mark when the menu opens, only for the next run
+    initial_marks: Option<Box<mark::ItemPredicate<T>>>,
+
+    // index in `item_list` of the item selected by the last run
+    selection: Option<usize>,
+
+    // structure of the items, `None` if no item has children
+    tree: Option<tree::Tree>,
+    // ids of the expanded items in the tree
+    expanded: std::collections::HashSet<u64>,
+    // id of the next item added to the menu
+    next_item_id: u64,
+
+    // keys that close the menu like enter, see `add_exit_key`
+    exit_keys: Vec<KeyEvent>,
+    // the exit key that closed the last run
+    exit_key: Option<KeyEvent>,
+    // callbacks bound to keys by `on_key`
+    key_bindings: Vec<binding::Binding<T>>,
+
+    // updates sent by the handles, see `Menu::handle`
+    handle_shared: std::sync::Arc<handle::Shared<T>>,
+
+    // code returned by a key callback to exit the last run
+    exit_code: Option<i32>,
+
+    // message displayed in place of the more/end tag until the next key
+    status: Option<String>,
+
+    // count prefix typed in normal mode, e.g. the `5` in `5j`
+    pending_count: Option<usize>,
+
+    // query to open the menu with in query mode, only for the next run
+    initial_query: Option<String>,
+
     // query mode fields
-    fuzzy_matcher: OnceCell<SkimMatcherV2>,
     query: String,
-    matched_item_indices: Vec<usize>,
+    // matches of the current query, items are never modified by matching
+    matches: query::MatchState,
     insert_idx: usize,
+    // results of previous queries, used to narrow down matching and to restore results instantly
+    query_cache: std::collections::HashMap<String, query::QueryResult>,
+    ranking: query::Ranking,
+    // what the items are matched against, shared with the match worker, built on the first query
+    haystack: Option<std::sync::Arc<query::Haystack>>,
+    // computes the final score of a matched item from the matcher's score
+    scorer: Option<Box<query::Scorer<T>>>,
+    // the scorer built for the current item list, shared with the match worker
+    item_scorer: Option<std::sync::Arc<query::ItemScorer>>,
+    // match the query against the descriptions too
+    search_description: bool,
+    pending_match: Option<query::PendingMatch>,
+    match_worker: Option<worker::MatchWorker>,
 
-    scroll_offset: u16,
+    scroll_offset: usize,
     max_height_percent: f32,
 
     show_end_tag: bool,
 
-    rayon_pool: OnceCell<rayon::ThreadPool>,
+    // strings drawn around the items, see their setters
+    pointer: String,
+    marker: String,
+    prompt: String,
+    more_tag: String,
+    end_tag: String,
+
+    // moving past either end of the list wraps around
+    cycle: bool,
+
+    enable_mouse: bool,
+
+    // colors are downsampled to what the terminal can display
+    color_depth: ColorDepth,
+    // time and item index of the last left click, used to detect double-clicks
+    last_click: Option<(std::time::Instant, usize)>,
+
+    // parallel matching settings, the pool is created on demand unless it's set by the user
+    parallel_matching: bool,
+    parallel_threshold: usize,
+    parallel_chunk_size: usize,
+    num_threads: Option<usize>,
+    rayon_pool: Option<std::sync::Arc<rayon::ThreadPool>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// criteria to order the matched items with the same score, see [`Menu::set_tiebreak`]
+pub enum Tiebreak {
+    /// prefer the item with shorter alias
+    Length,
+    /// prefer the item whose matched part begins earlier
+    Begin,
+    /// prefer the item whose matched part ends closer to the end
+    End,
+    /// prefer the item that was added earlier
+    Index,
 }
 
 #[derive(Clone, Copy)]
@@ -114,6 +260,16 @@ pub enum FontShape {
     Italic = 1,
     Bold = 1 << 1,
     Underline = 1 << 2,
+
+    // combinations produced by `|`
+    #[doc(hidden)]
+    BoldItalic = 1 | 1 << 1,
+    #[doc(hidden)]
+    ItalicUnderline = 1 | 1 << 2,
+    #[doc(hidden)]
+    BoldUnderline = 1 << 1 | 1 << 2,
+    #[doc(hidden)]
+    BoldItalicUnderline = 1 | 1 << 1 | 1 << 2,
 }
 
 #[derive(Default, Clone, Copy)]
@@ -122,31 +278,47 @@ pub enum FontShape {
 /// Examples:
 /// 1. set more than one shape at the same time
 /// ```no_run
+/// # use termenu::{FontShape, FontStyle};
 /// let mut style = FontStyle::default();
 /// style.set_shape(FontShape::Bold | FontShape::Italic);
 /// ```
 ///
 /// 2. set the fg/bg color by name
 /// ```no_run
+/// # use termenu::FontStyle;
+/// # use colored::Color;
 /// let mut style = FontStyle::default();
 /// style.set_fg_color(Color::Red);
 /// style.set_bg_color(Color::Blue);
 /// ```
 ///
-/// 3. set the fg/bg color by 256-color
+/// 3. set the fg/bg color by RGB or by index in the 256-color palette
 /// ```no_run
+/// # use termenu::FontStyle;
 /// let mut style = FontStyle::default();
-/// style.set_fg_color_256((255, 0, 0));
-/// style.set_bg_color_256((0, 0, 255));
+/// style.set_fg_color_rgb((255, 0, 0));
+/// style.set_bg_color_ansi256(236);
 /// ```
 ///
-/// **Note: if you set the fg/bg color by name, the 256-color will be ignored**
+/// **Note: the fg/bg color set last wins. Colors the terminal can't display are replaced with the
+/// closest ones it can, see [`ColorDepth`]**
 pub struct FontStyle {
     shape: Option<FontShape>,
-    fg_color: Option<Color>,
-    bg_color: Option<Color>,
-    fg_color_256: Option<(u8, u8, u8)>,
-    bg_color_256: Option<(u8, u8, u8)>,
+    fg_color: Option<color::StyleColor>,
+    bg_color: Option<color::StyleColor>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// colors the terminal can display, see [`Menu::set_color_depth`]
+pub enum ColorDepth {
+    /// no color at all, only bold, italic and underline
+    NoColor,
+    /// the 16 basic colors
+    Ansi16,
+    /// the 256-color palette
+    Ansi256,
+    /// 24-bit RGB colors
+    TrueColor,
 }
 
 #[derive(Clone, Copy)]
@@ -160,4 +332,13 @@ pub struct ColorScheme {
     matched: FontStyle,
     chosen_ln: FontStyle,
     more_tag: FontStyle,
+    // the styles of the more tag and the pointer when not set
+    end_tag: Option<FontStyle>,
+    pointer: FontStyle,
+    marker: Option<FontStyle>,
+    prompt: FontStyle,
+    chosen_bg: FontStyle,
+    description: FontStyle,
+    header: FontStyle,
+    disabled: FontStyle,
 }
@@ -1,10 +1,41 @@
-use clap::Parser;
 use clap::ValueEnum;
+use clap::{CommandFactory, Parser};
+use std::ffi::OsString;
+use std::fs::OpenOptions;
 use std::io::{self, BufRead};
-use termenu::{FontShape, FontStyle, Item};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use termenu::{
+    ColorDepth, ColorScheme, Frecency, Item, KeyCode, KeyEvent, KeyModifiers, MenuContext,
+    Response, Tiebreak,
+};
+
+// options prepended to the command line ones
+const DEFAULT_OPTS_ENV: &str = "TERMENU_DEFAULT_OPTS";
+
+// colorscheme the specs given by `--color` are applied on top of
+const DEFAULT_COLORS: &str = "title:bold:underline,query:italic,more-tag:magenta";
+
+macro_rules! quit_now {
+    ($content:expr, $($arg:tt)*) => {{
+        eprintln!($content, $($arg)*);
+        std::process::exit(1);
+    }};
+}
 
 #[derive(Parser, Debug)]
-#[command(version)]
+#[command(
+    version,
+    args_override_self = true,
+    after_help = "Default options are read from $XDG_CONFIG_HOME/termenu/config.toml (or \
+~/.config/termenu/config.toml) and then from the TERMENU_DEFAULT_OPTS environment variable, \
+the command line overrides both. An option given again replaces the earlier value, except \
+--bind and --color which accumulate.
+
+The keys of the config file are the long option names, e.g. `max-height = 0.5`, \
+`tiebreak = [\"length\"]` or `cycle = true`, `false` turns a flag off like `--no-<key>`. The [colors] table sets the style of the --color \
+elements, e.g. `title = \"bold underline\"` or `matched = \"#ff8700 on black\"`."
+)]
 struct Args {
     /// name of this operation, displays at the beginning.
     #[clap(short, long)]
@@ -17,13 +48,89 @@ struct Args {
 
     /// disable escape input, by default \n, \t, etc will be displayed as \\n, \\t, etc in single
     /// line
-    #[clap(short, long)]
+    #[clap(short, long, alias = "no-escape", overrides_with = "escape")]
     disable_escape: bool,
 
-    /// force color output
-    // #[arg(value_enum, default_value_t=ColorMode::Auto)]
-    #[clap(short, long, default_value = "auto")]
-    color: ColorMode,
+    /// escape the input, which is the default
+    #[clap(long, overrides_with = "disable_escape")]
+    escape: bool,
+
+    /// enable cyclic scrolling, moving past either end of the list wraps around
+    #[clap(long, overrides_with = "no_cycle")]
+    cycle: bool,
+
+    /// disable cyclic scrolling
+    #[clap(long, overrides_with = "cycle")]
+    no_cycle: bool,
+
+    /// enable mouse support, click to move the cursor and double-click to select
+    #[clap(long, overrides_with = "no_mouse")]
+    mouse: bool,
+
+    /// disable mouse support
+    #[clap(long, overrides_with = "mouse")]
+    no_mouse: bool,
+
+    /// hide the '---end---' tag displayed below the last item
+    #[clap(long, overrides_with = "end_tag")]
+    no_end_tag: bool,
+
+    /// string drawn in front of the item under the cursor, default is '> '
+    #[clap(long)]
+    pointer: Option<String>,
+
+    /// string drawn between the name and the query in query mode, default is ' /'
+    #[clap(long)]
+    prompt: Option<String>,
+
+    /// tag drawn below the items when they don't fit, default is '---more---'
+    #[clap(long)]
+    more_tag: Option<String>,
+
+    /// show the tag drawn below the last item, which is the default. with a value, the tag to
+    /// draw instead of '---end---'
+    #[clap(long, num_args = 0..=1, overrides_with = "no_end_tag")]
+    end_tag: Option<Option<String>>,
+
+    /// do not sort the matched items by score, keep the input order
+    #[clap(long, overrides_with = "sort")]
+    no_sort: bool,
+
+    /// sort the matched items by score, which is the default
+    #[clap(long, overrides_with = "no_sort")]
+    sort: bool,
+
+    /// comma-separated criteria to order the matched items with the same score, the input order
+    /// is always the last one
+    #[clap(long, value_delimiter = ',', action = clap::ArgAction::Set)]
+    tiebreak: Vec<TiebreakArg>,
+
+    /// number of threads to match large inputs in parallel, 0 disables parallel matching. default
+    /// is the number of CPUs
+    #[clap(long)]
+    threads: Option<usize>,
+
+    /// file to keep usage counts in, items that are selected more often and more recently rank
+    /// higher
+    #[clap(long)]
+    frecency: Option<std::path::PathBuf>,
+
+    /// comma-separated key bindings like `ctrl-r:reload(ls),ctrl-o:execute(less {})`. actions
+    /// are `execute(cmd)` to run a command and return to the menu, `reload(cmd)` to replace the
+    /// items with the output of a command, and `become(cmd)` to replace termenu with a command.
+    /// `{}` in a command is replaced with the current item and `{q}` with the query. can be
+    /// repeated, a later binding of the same key replaces an earlier one
+    #[clap(long, value_parser = parse_bindings)]
+    bind: Vec<Bindings>,
+
+    /// `auto`, `always` or `never` to tell when to color the output. otherwise a theme (dark,
+    /// light, 16-color or monochrome) or comma-separated `element:style` pairs like
+    /// `title:bold:green,matched:#ffaf00,chosen:black:on:#d7ff00`. elements are title, prompt,
+    /// query, items, matched, pointer, marker, chosen, chosen-bg, more-tag, end-tag, description,
+    /// header and disabled. styles are `bold`, `italic`, `underline`, a foreground color and a
+    /// background color after `on`. can be repeated, later specs override earlier ones
+    #[clap(short, long, value_parser = parse_color)]
+    color: Vec<ColorArg>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -33,6 +140,399 @@ enum ColorMode {
     Never,
 }
 
+#[derive(Clone, Debug)]
+enum ColorArg {
+    Mode(ColorMode),
+    // a valid colorscheme spec
+    Spec(String),
+}
+
+fn parse_color(input: &str) -> Result<ColorArg, String> {
+    if let Ok(mode) = ColorMode::from_str(input, true) {
+        return Ok(ColorArg::Mode(mode));
+    }
+    input.parse::<ColorScheme>()?;
+    Ok(ColorArg::Spec(input.to_string()))
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum TiebreakArg {
+    Length,
+    Begin,
+    End,
+    Index,
+}
+
+impl From<TiebreakArg> for Tiebreak {
+    fn from(arg: TiebreakArg) -> Self {
+        match arg {
+            TiebreakArg::Length => Tiebreak::Length,
+            TiebreakArg::Begin => Tiebreak::Begin,
+            TiebreakArg::End => Tiebreak::End,
+            TiebreakArg::Index => Tiebreak::Index,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    Execute(String),
+    Reload(String),
+    Become(String),
+}
+
+#[derive(Clone, Debug)]
+struct Bindings(Vec<(KeyEvent, Action)>);
+
+fn parse_bindings(input: &str) -> Result<Bindings, String> {
+    // commands may contain commas, only split at the ones outside of parentheses
+    let mut specs = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                specs.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    specs.push(&input[start..]);
+
+    let mut bindings = Vec::new();
+    for spec in specs {
+        let (key, action) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("invalid binding '{}', expected KEY:ACTION", spec))?;
+        bindings.push((parse_key(key)?, parse_action(action)?));
+    }
+    Ok(Bindings(bindings))
+}
+
+fn parse_key(name: &str) -> Result<KeyEvent, String> {
+    let single_char = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+    if let Some(c) = single_char(name) {
+        return Ok(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+    for (prefix, modifiers) in [
+        ("ctrl-", KeyModifiers::CONTROL),
+        ("alt-", KeyModifiers::ALT),
+    ] {
+        if let Some(c) = name.strip_prefix(prefix).and_then(single_char) {
+            return Ok(KeyEvent::new(
+                KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers,
+            ));
+        }
+    }
+    let code = match name {
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "btab" => return Ok(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)),
+        "esc" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "bspace" => KeyCode::Backspace,
+        "del" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pgup" => KeyCode::PageUp,
+        "pgdn" => KeyCode::PageDown,
+        _ => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return Err(format!("unknown key '{}'", name)),
+        },
+    };
+    Ok(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn parse_action(action: &str) -> Result<Action, String> {
+    let (name, cmd) = action
+        .strip_suffix(')')
+        .and_then(|action| action.split_once('('))
+        .ok_or_else(|| format!("invalid action '{}', expected NAME(COMMAND)", action))?;
+    let cmd = cmd.to_string();
+    match name {
+        "execute" => Ok(Action::Execute(cmd)),
+        "reload" => Ok(Action::Reload(cmd)),
+        "become" => Ok(Action::Become(cmd)),
+        _ => Err(format!("unknown action '{}'", name)),
+    }
+}
+
+impl Action {
+    fn run(&self, ctx: &mut MenuContext<String>, disable_escape: bool) -> Response {
+        let current = ctx.current().map(|item| item.value.as_str()).unwrap_or("");
+        let cmd = |cmd: &str| shell(&expand_placeholders(cmd, current, ctx.query()));
+        // errors are displayed in the status line, exiting here would leave the terminal in raw mode
+        let result = match self {
+            Action::Execute(cmd_str) => {
+                let mut cmd = cmd(cmd_str);
+                ctx.suspend(|| cmd.stdin(tty()).stdout(tty()).status().map(|_| ()))
+                    .and_then(|result| result)
+            }
+            Action::Reload(cmd_str) => cmd(cmd_str)
+                .stdin(Stdio::null())
+                .output()
+                .and_then(|output| match output.status.success() {
+                    true => read_items(&output.stdout[..], disable_escape),
+                    false => Err(io::Error::other(format!(
+                        "`{}` failed with {}",
+                        cmd_str, output.status
+                    ))),
+                })
+                .map(|items| ctx.replace_items(items)),
+            Action::Become(cmd_str) => {
+                let mut cmd = cmd(cmd_str);
+                cmd.stdin(tty());
+                ctx.suspend(|| become_command(cmd)).and_then(Err)
+            }
+        };
+        if let Err(e) = result {
+            ctx.set_status(&format!("Error: {}", e));
+        }
+        Response::Continue
+    }
+}
+
+// replace `{}` with the current item and `{q}` with the query, both quoted for the shell. the
+// template is scanned once, so the replacements are never expanded again
+fn expand_placeholders(template: &str, current: &str, query: &str) -> String {
+    let mut cmd = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find('{') {
+        cmd.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if let Some(after) = rest.strip_prefix("{}") {
+            cmd.push_str(&shell_quote(current));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{q}") {
+            cmd.push_str(&shell_quote(query));
+            rest = after;
+        } else {
+            cmd.push('{');
+            rest = &rest[1..];
+        }
+    }
+    cmd.push_str(rest);
+    cmd
+}
+
+fn shell(cmd: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmd);
+    command
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+// commands run by actions talk to the terminal even when termenu is used in a pipe
+fn tty() -> Stdio {
+    match OpenOptions::new().read(true).write(true).open("/dev/tty") {
+        Ok(tty) => Stdio::from(tty),
+        Err(_) => Stdio::inherit(),
+    }
+}
+
+// replace termenu with the command, only return if it can't be run
+#[cfg(unix)]
+fn become_command(mut cmd: Command) -> io::Error {
+    use std::os::unix::process::CommandExt;
+    cmd.exec()
+}
+
+#[cfg(not(unix))]
+fn become_command(mut cmd: Command) -> io::Error {
+    match cmd.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => e,
+    }
+}
+
+fn read_items(mut reader: impl BufRead, disable_escape: bool) -> io::Result<Vec<Item<String>>> {
+    let mut items = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break; //EOF
+        }
+        // invalid UTF-8 is replaced rather than rejected, the input may be any command's output
+        let line = String::from_utf8_lossy(&buf)
+            .trim_end_matches('\n')
+            .to_string();
+        let alias = match disable_escape {
+            true => line.clone(),
+            false => line.escape_default().to_string(),
+        };
+        items.push(Item::new(&alias, line));
+    }
+    Ok(items)
+}
+
+fn config_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("termenu").join("config.toml"))
+}
+
+/// options read from the config file, `None` if the file doesn't exist
+fn read_config(path: &Path) -> Result<Option<Vec<String>>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e: toml::de::Error| e.to_string().trim_end().to_string())?;
+
+    let mut args = Vec::new();
+    for (key, value) in table {
+        if key == "colors" {
+            args.extend(color_args(value)?);
+            continue;
+        }
+        if !is_option(&key) {
+            return Err(format!("unknown option '{}'", key));
+        }
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                toml::Value::Boolean(true) => args.push(format!("--{}", key)),
+                toml::Value::Boolean(false) => args.push(negated_option(&key)?),
+                toml::Value::String(s) => args.push(format!("--{}={}", key, s)),
+                toml::Value::Integer(n) => args.push(format!("--{}={}", key, n)),
+                toml::Value::Float(n) => args.push(format!("--{}={}", key, n)),
+                _ => return Err(format!("invalid value for '{}'", key)),
+            }
+        }
+    }
+    validate_default_opts(&args)?;
+    Ok(Some(args))
+}
+
+fn is_option(name: &str) -> bool {
+    Args::command().get_arguments().any(|arg| {
+        arg.get_long() == Some(name)
+            || arg
+                .get_all_aliases()
+                .into_iter()
+                .flatten()
+                .any(|a| a == name)
+    })
+}
+
+// `key = false` as the opposite option, `--no-<key>` or `--<key>` for `no-<key> = false`
+fn negated_option(key: &str) -> Result<String, String> {
+    let negated = match key.strip_prefix("no-") {
+        Some(positive) => positive.to_string(),
+        None => format!("no-{}", key),
+    };
+    match is_option(&negated) {
+        true => Ok(format!("--{}", negated)),
+        false => Err(format!("'{}' can't be false", key)),
+    }
+}
+
+// the [colors] table as `--color` options
+fn color_args(value: toml::Value) -> Result<Vec<String>, String> {
+    let table = match value {
+        toml::Value::Table(table) => table,
+        _ => return Err("'colors' should be a table".to_string()),
+    };
+    let mut args = Vec::new();
+    for (element, style) in table {
+        match style {
+            // a comma would start another element of the spec
+            toml::Value::String(style) if style.contains(',') => {
+                return Err(format!("'colors.{}' can't contain ','", element))
+            }
+            toml::Value::String(style) => args.push(format!("--color={}:{}", element, style)),
+            _ => return Err(format!("'colors.{}' should be a string", element)),
+        }
+    }
+    Ok(args)
+}
+
+// parse the default options alone, so an error tells where they come from
+fn validate_default_opts(opts: &[String]) -> Result<(), String> {
+    let argv = std::iter::once("termenu").chain(opts.iter().map(String::as_str));
+    match Args::try_parse_from(argv) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let rendered = e.to_string();
+            let first_line = rendered.lines().next().unwrap_or_default();
+            Err(first_line.trim_start_matches("error: ").to_string())
+        }
+    }
+}
+
+fn default_opts_from_env() -> Result<Vec<String>, String> {
+    let opts = match std::env::var(DEFAULT_OPTS_ENV) {
+        Ok(opts) => opts,
+        Err(std::env::VarError::NotPresent) => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    split_default_opts(&opts)
+}
+
+fn split_default_opts(opts: &str) -> Result<Vec<String>, String> {
+    let opts = shlex::split(opts).ok_or("unbalanced quotes")?;
+    validate_default_opts(&opts)?;
+    Ok(opts)
+}
+
+/// parse the command line after the default options of the config file and the env var, a
+/// later option overrides an earlier one
+fn parse_args() -> Args {
+    let config = match config_path() {
+        Some(path) => read_config(&path)
+            .unwrap_or_else(|e| quit_now!("Error: invalid config {}: {}", path.display(), e)),
+        None => None,
+    };
+    let env_opts = default_opts_from_env()
+        .unwrap_or_else(|e| quit_now!("Error: invalid {}: {}", DEFAULT_OPTS_ENV, e));
+
+    merge_args(config.unwrap_or_default(), env_opts, std::env::args_os())
+        .unwrap_or_else(|e| e.exit())
+}
+
+/// parse the command line `argv` as if the config and env options were given first. options
+/// given again replace the earlier value, except `--bind` and `--color` which accumulate
+fn merge_args<I>(config: Vec<String>, env: Vec<String>, argv: I) -> Result<Args, clap::Error>
+where
+    I: IntoIterator,
+    I::Item: Into<OsString>,
+{
+    let mut argv = argv.into_iter().map(Into::into);
+    let argv = argv
+        .next()
+        .into_iter()
+        .chain(config.into_iter().map(Into::into))
+        .chain(env.into_iter().map(Into::into))
+        .chain(argv);
+    Args::try_parse_from(argv)
+}
+
 fn validate_max_height(input: &str) -> Result<f32, String> {
     match input.parse::<f32>() {
         Ok(n) if n > 0.0 && n <= 1.0 => Ok(n),
@@ -40,63 +540,266 @@ fn validate_max_height(input: &str) -> Result<f32, String> {
     }
 }
 
-macro_rules! quit_now {
-    ($content:expr, $($arg:tt)*) => {{
-        eprintln!($content, $($arg)*);
-        std::process::exit(1);
-    }};
-}
-
 fn main() {
-    let args = Args::parse();
-    match args.color {
+    let args = parse_args();
+    let mut specs = vec![DEFAULT_COLORS];
+    let mut mode = ColorMode::Auto;
+    for color in &args.color {
+        match color {
+            ColorArg::Mode(m) => mode = *m,
+            ColorArg::Spec(spec) => specs.push(spec),
+        }
+    }
+    let mut color_depth = ColorDepth::detect();
+    match mode {
         ColorMode::Auto => {}
-        ColorMode::Always => colored::control::set_override(true),
+        ColorMode::Always => {
+            colored::control::set_override(true);
+            // colors are forced even if NO_COLOR or TERM tells otherwise
+            color_depth = color_depth.max(ColorDepth::Ansi16);
+        }
         ColorMode::Never => colored::control::set_override(false),
     }
     let mut menu = termenu::Menu::new().unwrap_or_else(|e| quit_now!("Error: {}", e));
     args.name.map(|name| menu.set_title(&name));
     args.max_height.map(|percent| menu.set_max_height(percent));
+    menu.set_cycle(args.cycle);
+    menu.show_end_tag(!args.no_end_tag);
+    args.pointer.map(|pointer| menu.set_pointer(&pointer));
+    args.prompt.map(|prompt| menu.set_prompt(&prompt));
+    args.more_tag.map(|tag| menu.set_more_tag(&tag));
+    args.end_tag.flatten().map(|tag| menu.set_end_tag(&tag));
+    menu.enable_mouse(args.mouse);
+    match args.threads {
+        Some(0) => {
+            menu.enable_parallel_matching(false);
+        }
+        Some(n) => {
+            menu.set_num_threads(n);
+        }
+        None => {}
+    }
+    menu.set_sort(!args.no_sort);
+    let tiebreak: Vec<Tiebreak> = args.tiebreak.into_iter().map(Tiebreak::from).collect();
+    menu.set_tiebreak(&tiebreak);
+    let mut frecency = args.frecency.map(|path| {
+        let frecency =
+            Frecency::open(&path).unwrap_or_else(|e| quit_now!("Error: {}: {}", path.display(), e));
+        menu.set_scorer(frecency.scorer());
+        frecency
+    });
 
-    let mut colorscheme = termenu::ColorScheme::default();
-    colorscheme
-        .set_title_style(
-            FontStyle::default()
-                .set_shape(FontShape::Bold | FontShape::Underline)
-                .build(),
-        )
-        .set_query_style(FontStyle::default().set_shape(FontShape::Italic).build())
-        .set_more_tag_style(
-            FontStyle::default()
-                .set_fg_color(colored::Color::Magenta)
-                .build(),
-        );
+    let colorscheme: ColorScheme = specs
+        .join(",")
+        .parse()
+        .unwrap_or_else(|e| quit_now!("Error: {}", e));
     menu.set_colorscheme(colorscheme);
+    menu.set_color_depth(color_depth);
     menu.enable_print_result(false);
 
-    let stdin = io::stdin();
-    loop {
-        let mut buf = String::new();
-        match stdin.lock().read_line(&mut buf) {
-            Err(e) => quit_now!("Error: {}", e),
-            Ok(0) => break, //EOF
-            _ => {
-                buf = buf.trim_end_matches('\n').to_string();
-                if !args.disable_escape {
-                    buf = buf.escape_default().to_string();
-                }
-                menu.add(Item::new(&buf, ()))
-            }
-        };
+    let disable_escape = args.disable_escape;
+    for (key, action) in args.bind.into_iter().flat_map(|bindings| bindings.0) {
+        menu.on_key(key, move |ctx| action.run(ctx, disable_escape));
     }
 
+    let items = read_items(io::stdin().lock(), disable_escape)
+        .unwrap_or_else(|e| quit_now!("Error: {}", e));
+    menu.add_list(items);
+
     let selection = menu
         .select_item()
         .unwrap_or_else(|e| quit_now!("Error: {}", e));
     if let Some(item) = selection {
+        if let Some(frecency) = frecency.as_mut() {
+            frecency.record(&item.alias);
+            frecency
+                .save()
+                .unwrap_or_else(|e| quit_now!("Error: {}", e));
+        }
         print!("{}", item.alias)
     } else {
         drop(menu);
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(input: &str) -> Vec<(KeyEvent, String)> {
+        let bindings = parse_bindings(input).unwrap().0;
+        bindings
+            .into_iter()
+            .map(|(key, action)| (key, format!("{:?}", action)))
+            .collect()
+    }
+
+    #[test]
+    fn parse_bindings_splits_outside_parentheses() {
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        assert_eq!(
+            bindings("ctrl-r:reload(ls -a, b),ctrl-O:execute(less {}),f5:become(vi {q})"),
+            [
+                (ctrl('r'), r#"Reload("ls -a, b")"#.to_string()),
+                (ctrl('o'), r#"Execute("less {}")"#.to_string()),
+                (
+                    KeyEvent::from(KeyCode::F(5)),
+                    r#"Become("vi {q}")"#.to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            bindings("x:execute(echo a:b)"),
+            [(
+                KeyEvent::from(KeyCode::Char('x')),
+                r#"Execute("echo a:b")"#.to_string()
+            )]
+        );
+        assert_eq!(
+            bindings("btab:execute(true)")[0].0,
+            KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)
+        );
+    }
+
+    #[test]
+    fn parse_bindings_rejects_invalid_specs() {
+        for input in [
+            "ctrl-r",
+            "ctrl-r:reload",
+            "ctrl-r:reload(ls",
+            "ctrl-r:print(ls)",
+            "f13:reload(ls)",
+            "ctrl-xy:reload(ls)",
+        ] {
+            assert!(parse_bindings(input).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn placeholders_are_quoted() {
+        assert_eq!(
+            expand_placeholders("less {} # {q}", "it's", "a b"),
+            r"less 'it'\''s' # 'a b'"
+        );
+        assert_eq!(expand_placeholders("echo {x} {", "a", "b"), "echo {x} {");
+    }
+
+    #[test]
+    fn placeholders_are_not_expanded_twice() {
+        let cmd = expand_placeholders("echo {q} {}", "{x}; touch pwned", "{}");
+        assert_eq!(cmd, "echo '{}' '{x}; touch pwned'");
+
+        let output = shell(&cmd).output().unwrap();
+        assert_eq!(output.stdout, b"{} {x}; touch pwned\n");
+    }
+
+    #[test]
+    fn later_sources_override_earlier_ones() {
+        let path = std::env::temp_dir().join(format!("termenu-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+max-height = 0.5
+cycle = true
+tiebreak = ["length", "index"]
+bind = "ctrl-a:execute(true)"
+
+[colors]
+title = "red"
+"#,
+        )
+        .unwrap();
+        let config = read_config(&path).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let env =
+            split_default_opts("--no-cycle --tiebreak=end --bind 'ctrl-b:reload(ls)'").unwrap();
+        let argv = [
+            "termenu",
+            "--tiebreak",
+            "begin",
+            "--color",
+            "title:blue",
+            "-m",
+            "0.3",
+        ];
+
+        let args = merge_args(config.clone(), env.clone(), argv).unwrap();
+        assert_eq!(args.max_height, Some(0.3));
+        assert!(!args.cycle);
+        assert_eq!(args.tiebreak, [TiebreakArg::Begin]);
+        // bindings and colors accumulate in order
+        let keys: Vec<_> = args
+            .bind
+            .iter()
+            .flat_map(|b| &b.0)
+            .map(|(key, _)| key.code)
+            .collect();
+        assert_eq!(keys, [KeyCode::Char('a'), KeyCode::Char('b')]);
+        let specs: Vec<_> = args
+            .color
+            .iter()
+            .map(|color| match color {
+                ColorArg::Spec(spec) => spec.as_str(),
+                ColorArg::Mode(_) => "",
+            })
+            .collect();
+        assert_eq!(specs, ["title:red", "title:blue"]);
+
+        // the config file applies when nothing else sets the option, a list is replaced whole
+        let args = merge_args(config, env, ["termenu", "--tiebreak=length,begin"]).unwrap();
+        assert_eq!(args.max_height, Some(0.5));
+        assert_eq!(args.tiebreak, [TiebreakArg::Length, TiebreakArg::Begin]);
+    }
+
+    #[test]
+    fn config_colors_are_single_elements() {
+        let colors =
+            |content: &str| color_args(content.parse::<toml::Value>().unwrap()["colors"].clone());
+        assert_eq!(
+            colors("[colors]\ncurrent = \"black on-#d7ff00\"").unwrap(),
+            ["--color=current:black on-#d7ff00"]
+        );
+        assert_eq!(
+            colors("[colors]\ntitle = \"red,matched:blue\"").unwrap_err(),
+            "'colors.title' can't contain ','"
+        );
+    }
+
+    #[test]
+    fn false_in_the_config_negates_a_flag() {
+        let path = std::env::temp_dir().join(format!("termenu-false-{}.toml", std::process::id()));
+        let read = |content: &str| {
+            std::fs::write(&path, content).unwrap();
+            let config = read_config(&path);
+            std::fs::remove_file(&path).unwrap();
+            config
+        };
+
+        let config = read("cycle = false\nend-tag = false\nescape = false\nno-sort = false\n");
+        let config = config.unwrap().unwrap();
+        assert_eq!(
+            config,
+            ["--no-cycle", "--no-end-tag", "--no-escape", "--sort"]
+        );
+        let args = merge_args(config.clone(), vec![], ["termenu", "--cycle"]).unwrap();
+        assert!(args.cycle && args.no_end_tag && args.disable_escape && !args.no_sort);
+
+        // the positive flags turn them back on
+        let argv = ["termenu", "--end-tag", "--escape"];
+        let args = merge_args(config, vec![], argv).unwrap();
+        assert!(!args.no_end_tag && !args.disable_escape);
+        assert_eq!(args.end_tag, Some(None));
+        let args = merge_args(vec![], vec![], ["termenu", "--end-tag=-fin-"]).unwrap();
+        assert_eq!(args.end_tag, Some(Some("-fin-".to_string())));
+
+        assert_eq!(
+            read("threads = false").unwrap_err(),
+            "'threads' can't be false"
+        );
+        assert_eq!(
+            read("disable-escape = false").unwrap_err(),
+            "'disable-escape' can't be false"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::io;
+
+use crate::{keymap::KeyResponse, Item, Menu};
+
+/// tells if an item is the one looked for, see [Menu::set_marked_by] and [Menu::set_cursor_by]
+pub(crate) type ItemPredicate<T> = dyn Fn(&Item<T>) -> bool + Send;
+
+// multi-select
+impl<T> Menu<T> {
+    /// Set if items can be marked with `tab` (mark and move down) and `shift-tab` (mark and move
+    /// up), in both normal and query mode. Marking a marked item unmarks it. Default is `false`.
+    ///
+    /// Marked items are drawn with a marker, and they stay marked across queries and runs until
+    /// the menu is [reset](Self::reset). Disabled items and items with children can't be marked.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Item, Menu};
+    /// let mut menu = Menu::new().unwrap();
+    /// menu.add_list(vec![Item::new("a.txt", 1), Item::new("b.txt", 2)])
+    ///     .enable_multi_select(true);
+    /// for item in menu.select_multi().unwrap() {
+    ///     println!("{}", item.alias);
+    /// }
+    /// ```
+    pub fn enable_multi_select(&mut self, b: bool) -> &mut Self {
+        self.multi_select = b;
+        self
+    }
+
+    /// Mark the items matching the predicate when the menu opens, e.g. the ones that are
+    /// currently enabled in a settings list. See [enable_multi_select](Self::enable_multi_select).
+    ///
+    /// It only applies to the next run of the menu, and it works together with
+    /// [set_cursor](Self::set_cursor) and [set_cursor_by](Self::set_cursor_by).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Item, Menu};
+    /// let enabled = ["rust", "go"];
+    /// let mut menu = Menu::new().unwrap();
+    /// menu.add_list(vec![Item::new("rust", 1), Item::new("go", 2), Item::new("c", 3)])
+    ///     .enable_multi_select(true)
+    ///     .set_marked_by(move |item| enabled.contains(&item.alias.as_str()))
+    ///     .set_cursor_by(|item| item.alias == "go");
+    /// ```
+    pub fn set_marked_by<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: Fn(&Item<T>) -> bool + Send + 'static,
+    {
+        self.initial_marks = Some(Box::new(predicate));
+        self
+    }
+
+    /// The marked items, in the order they're listed
+    pub fn marked(&self) -> Vec<&Item<T>> {
+        self.item_list
+            .iter()
+            .filter(|item| self.marked.contains(&item.id))
+            .collect()
+    }
+
+    /// Same as [select_item](Self::select_item), but return the marked items in the order
+    /// they're listed, or the selected item if none is marked. The list is empty if nothing is
+    /// selected.
+    pub fn select_multi(&mut self) -> io::Result<Vec<&Item<T>>> {
+        let idx = match self.select0()? {
+            Some(idx) => idx,
+            None => return Ok(Vec::new()),
+        };
+        let marked = self.marked();
+        match marked.is_empty() {
+            true => Ok(vec![&self.item_list[idx]]),
+            false => Ok(marked),
+        }
+    }
+
+    pub(crate) fn is_marked(&self, idx: usize) -> bool {
+        self.marked.contains(&self.item_list[idx].id)
+    }
+
+    // only what can be selected can be marked
+    fn can_mark(&self, idx: usize) -> bool {
+        self.item_list[idx].enabled && !self.has_children(idx)
+    }
+
+    pub(crate) fn mark_matching(&mut self, predicate: Box<ItemPredicate<T>>) {
+        for idx in 0..self.item_list.len() {
+            if self.can_mark(idx) && predicate(&self.item_list[idx]) {
+                self.marked.insert(self.item_list[idx].id);
+            }
+        }
+    }
+
+    /// toggle the mark of the item under the cursor and move to the next (or previous) one
+    pub(crate) fn key_toggle_mark(&mut self, forward: bool) -> io::Result<KeyResponse> {
+        self.pending_count = None;
+        let idx = match self.item_idx_at(self.cursor_idx()) {
+            Some(idx) if self.can_mark(idx) => idx,
+            _ => return Ok(KeyResponse::new(false, false)),
+        };
+        let id = self.item_list[idx].id;
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        match forward {
+            true => self.key_down(1)?,
+            false => self.key_up(1)?,
+        };
+        Ok(KeyResponse::new(false, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::{Item, Menu};
+
+    fn press(menu: &mut Menu<usize>, code: KeyCode) {
+        menu.dispatch_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+
+    fn current(menu: &Menu<usize>) -> usize {
+        menu.item_at(menu.cursor_idx()).unwrap().value
+    }
+
+    fn marked(menu: &Menu<usize>) -> Vec<usize> {
+        menu.marked().iter().map(|item| item.value).collect()
+    }
+
+    fn numbered_menu(cnt: usize) -> Menu<usize> {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list((1..=cnt).map(|i| Item::new(&i.to_string(), i)).collect())
+            .enable_multi_select(true);
+        menu
+    }
+
+    #[test]
+    fn tab_toggles_marks() {
+        let mut menu = numbered_menu(5);
+        press(&mut menu, KeyCode::Tab);
+        press(&mut menu, KeyCode::Tab);
+        assert_eq!(marked(&menu), [1, 2]);
+        assert_eq!(current(&menu), 3);
+
+        press(&mut menu, KeyCode::BackTab);
+        assert_eq!(current(&menu), 2);
+        press(&mut menu, KeyCode::BackTab);
+        assert_eq!(marked(&menu), [1, 3]);
+        assert_eq!(current(&menu), 1);
+
+        // the marks are kept in query mode
+        press(&mut menu, KeyCode::Char('/'));
+        press(&mut menu, KeyCode::Char('5'));
+        while menu.is_matching() {
+            menu.receive_matches();
+            std::thread::yield_now();
+        }
+        press(&mut menu, KeyCode::Tab);
+        assert_eq!(marked(&menu), [1, 3, 5]);
+    }
+
+    #[test]
+    fn tab_is_ignored_without_multi_select() {
+        let mut menu = numbered_menu(5);
+        menu.enable_multi_select(false);
+        press(&mut menu, KeyCode::Tab);
+        assert!(marked(&menu).is_empty());
+        assert_eq!(current(&menu), 1);
+    }
+
+    #[test]
+    fn initial_state_is_resolved_when_the_menu_opens() {
+        let mut menu = numbered_menu(5);
+        menu.set_cursor_by(|item| item.value == 40)
+            .set_marked_by(|item| item.value % 20 == 0);
+        // added after the calls, and before the items they're looking for
+        menu.add_group("tens", (1..=5).map(|i| Item::new("", i * 10)).collect());
+        menu.apply_initial_state().unwrap();
+        assert_eq!(current(&menu), 40);
+        assert_eq!(marked(&menu), [20, 40]);
+
+        // it only applies to one run
+        press(&mut menu, KeyCode::Up);
+        menu.apply_initial_state().unwrap();
+        assert_eq!(current(&menu), 30);
+    }
+
+    #[test]
+    fn cursor_stays_on_the_same_item_until_the_menu_opens() {
+        let mut menu = numbered_menu(5);
+        menu.set_cursor(2);
+        menu.remove_while_open(|_, item| item.value == 1);
+        menu.apply_initial_state().unwrap();
+        assert_eq!(current(&menu), 3);
+    }
+}
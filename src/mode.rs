@@ -2,16 +2,20 @@ use std::io;
 
 use crate::{macros::*, Menu, Mode};
 
-impl<T: Send + Sync> Menu<T> {
+impl<T> Menu<T> {
     pub(crate) fn enter_normal_mode(&mut self) -> io::Result<()> {
         self.mode = Mode::Normal;
-        self.matched_item_indices.clear();
+        self.cancel_matching();
+        self.matches.clear();
+        // the cursor must not rest on a disabled item
+        self.move_to(self.cursor_idx())?;
         term_exec!(crossterm::cursor::Hide);
         Ok(())
     }
 
     pub(crate) fn enter_query_mode(&mut self) -> io::Result<()> {
         self.mode = Mode::Query;
+        self.pending_count = None;
         self.query = String::new();
         self.insert_idx = 0;
         term_exec!(crossterm::cursor::Show);
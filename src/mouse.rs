@@ -0,0 +1,74 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+
+use crate::{keymap::KeyResponse, Menu};
+
+// two clicks on the same row within this interval are treated as a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+impl<T> Menu<T> {
+    pub(crate) fn dispatch_mouse(&mut self, evt: MouseEvent) -> io::Result<KeyResponse> {
+        match evt.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.mouse_click(evt.row),
+
+            MouseEventKind::ScrollUp => self.move_to(self.cursor_idx().saturating_sub(1)),
+
+            MouseEventKind::ScrollDown => self.move_to(self.cursor_idx() + 1),
+
+            _ => Ok(KeyResponse::new(false, false)),
+        }
+    }
+
+    fn mouse_click(&mut self, row: u16) -> io::Result<KeyResponse> {
+        let idx = match self.row_to_item_idx(row) {
+            Some(idx) => idx,
+            None => {
+                self.last_click = None;
+                return Ok(KeyResponse::new(false, false));
+            }
+        };
+
+        let now = Instant::now();
+        if let Some((at, last_idx)) = self.last_click.take() {
+            if last_idx == idx && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL {
+                self.move_to(idx)?;
+                return self.key_enter();
+            }
+        }
+        self.last_click = Some((now, idx));
+
+        self.move_to(idx)
+    }
+
+    /// translate an absolute terminal row into the index of the item drawn on it
+    fn row_to_item_idx(&self, row: u16) -> Option<usize> {
+        // the first item is drawn right below the title
+        let (title_row, _) = self.cursor_abs_pos;
+        if row <= title_row {
+            return None;
+        }
+        let offset = (row - title_row - 1) as usize;
+        if offset >= self.visible_height() {
+            return None;
+        }
+        // walk down the rows, group headers take a row of their own
+        let mut rows = 0;
+        for idx in self.scroll_offset..self.item_count() {
+            if self.header_above(idx).is_some() {
+                if rows == offset {
+                    return None;
+                }
+                rows += 1;
+            }
+            if rows == offset {
+                return Some(idx);
+            }
+            rows += 1;
+        }
+        None
+    }
+}
@@ -1,83 +1,850 @@
+use std::{cmp::Reverse, sync::Arc};
+
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 
-use crate::Menu;
+use crate::{
+    worker::{Job, MatchWorker, Progress},
+    Item, Menu, Tiebreak,
+};
+
+// max number of candidates and matched items kept in the cache over all queries, the cache is
+// dropped as a whole when a new result doesn't fit. the latest result is always kept
+const QUERY_CACHE_CAPACITY: usize = 2_000_000;
+
+pub(crate) const DEFAULT_PARALLEL_THRESHOLD: usize = 10000;
+pub(crate) const DEFAULT_PARALLEL_CHUNK_SIZE: usize = 50;
+
+/// (item index, score, matched char indices) of a matched item
+pub(crate) type Matched = (usize, i64, Vec<usize>);
+
+/// how matched items are ordered
+#[derive(Clone)]
+pub(crate) struct Ranking {
+    // sort by score, otherwise keep the input order
+    pub(crate) sort: bool,
+    // criteria to order items with the same score, the input order is always the last one
+    pub(crate) tiebreak: Vec<Tiebreak>,
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Ranking {
+            sort: true,
+            tiebreak: Vec::new(),
+        }
+    }
+}
+
+/// computes the final score of the item with the given index from the matcher's score, it runs
+/// on the match worker
+pub(crate) type ItemScorer = dyn Fn(usize, i64) -> i64 + Send + Sync;
 
-impl<T: Send + Sync> Menu<T> {
+/// builds the [ItemScorer] of a custom scorer from the item list, whenever the list changes
+pub(crate) type Scorer<T> = dyn Fn(&[Item<T>]) -> Arc<ItemScorer> + Send;
+
+/// what the match worker matches against, built from the item list on the first query
+pub(crate) struct Haystack {
+    // search texts of the items, indexed by item index
+    pub(crate) texts: Vec<String>,
+    // number of chars in the aliases, the length tiebreaks are computed on the aliases, since
+    // they're what is displayed
+    pub(crate) alias_lens: Vec<usize>,
+    // weights added to the scores of the items, `None` if a custom scorer is set or every
+    // weight is 0
+    pub(crate) weights: Option<Vec<i64>>,
+    // index of the group of each item in order of appearance, `None` if there are no groups.
+    // matched items are ranked within their groups, so the groups are never interleaved
+    pub(crate) groups: Option<Vec<usize>>,
+}
+
+impl Haystack {
+    pub(crate) fn rank_key(&self, m: &Matched, ranking: &Ranking) -> RankKey {
+        let group = self.groups.as_ref().map_or(0, |groups| groups[m.0]);
+        rank_key(group, m.0, m.1, &m.2, &self.alias_lens, ranking)
+    }
+}
+
+/// how to match large lists in parallel
+#[derive(Clone)]
+pub(crate) struct Parallel {
+    pub(crate) pool: Arc<rayon::ThreadPool>,
+    // jobs with more candidates than this are matched in parallel
+    pub(crate) threshold: usize,
+    // number of candidates matched by a thread at a time
+    pub(crate) chunk_size: usize,
+}
+
+/// result of matching a query against the item list
+pub(crate) struct QueryResult {
+    // indices of the items that contain the query as a subsequence, whatever their score is.
+    // a query that contains this one as a subsequence can only match items in here
+    pub(crate) candidates: Arc<[usize]>,
+    // the items to display, only the leading `ranked` ones are in their final order. shared
+    // with the match state, so restoring a cached result copies nothing
+    pub(crate) matched: Arc<[Matched]>,
+    pub(crate) ranked: usize,
+}
+
+impl QueryResult {
+    // how much of the cache capacity it takes
+    fn size(&self) -> usize {
+        1 + self.candidates.len() + self.matched.len()
+    }
+}
+
+/// matches of the current query. it's kept apart from the items, so the item list is never
+/// modified by matching and no stale score survives a query change
+#[derive(Default)]
+pub(crate) struct MatchState {
+    // the items to display, only the leading `ranked` ones are in their final order. it's
+    // copied from the cached result only if more of it has to be ranked
+    matched: Arc<[Matched]>,
+    ranked: usize,
+}
+
+impl MatchState {
+    pub(crate) fn len(&self) -> usize {
+        self.matched.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.matched.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.matched = Arc::default();
+        self.ranked = 0;
+    }
+
+    /// index in the item list of the `pos`th match
+    pub(crate) fn item_idx(&self, pos: usize) -> Option<usize> {
+        self.matched.get(pos).map(|m| m.0)
+    }
+
+    /// matched char indices of the `pos`th match
+    pub(crate) fn indices(&self, pos: usize) -> &[usize] {
+        self.matched.get(pos).map_or(&[], |m| m.2.as_slice())
+    }
+
+    fn position(&self, item_idx: usize) -> Option<usize> {
+        self.matched.iter().position(|m| m.0 == item_idx)
+    }
+}
+
+/// a query that is being matched in the background
+pub(crate) struct PendingMatch {
+    query: String,
+    // item to keep the cursor on once the result is ready
+    keep_cursor_on: Option<usize>,
+    // whether any progress of this query has been applied yet
+    received: bool,
+}
+
+impl<T> Menu<T> {
+    /// start matching the current query, the cursor is moved to the first match
     pub(crate) fn fuzzy_match(&mut self) {
-        // reset
-        self.selection_idx = 0;
-        self.scroll_offset = 0;
-
-        if self.item_list.len() > 10000 {
-            self.fuzzy_match_rayon();
-        } else {
-            self.matched_item_indices = Vec::new();
-            // match
-            let matcher = self.fuzzy_matcher.get_or_init(SkimMatcherV2::default);
-            for (i, item) in self.item_list.iter_mut().enumerate() {
-                match matcher.fuzzy_indices(&item.alias, &self.query) {
-                    None => continue,
-                    Some((score, indices)) => {
-                        if score <= 0 {
-                            continue;
-                        }
-                        item.score = Some(score);
-                        item.matched_indices = Some(indices);
-                        self.matched_item_indices.push(i);
-                    }
+        self.start_matching(None);
+    }
+
+    /// start matching the current query, the cursor stays on the given item if it still matches
+    pub(crate) fn start_matching(&mut self, keep_cursor_on: Option<usize>) {
+        self.pending_match = Some(PendingMatch {
+            query: self.query.clone(),
+            keep_cursor_on,
+            received: false,
+        });
+
+        if self.query_cache.contains_key(&self.query) {
+            if let Some(worker) = &self.match_worker {
+                worker.cancel();
+            }
+            self.apply_cached_result();
+            return;
+        }
+
+        // narrow down from the smallest cached result whose query is a subsequence of the
+        // current one, so only its candidates have to be matched again
+        let candidates = self
+            .query_cache
+            .iter()
+            .filter(|(query, _)| is_subsequence(query, &self.query))
+            .map(|(_, result)| &result.candidates)
+            .min_by_key(|candidates| candidates.len())
+            .cloned();
+        let haystack = match &self.haystack {
+            Some(haystack) => haystack.clone(),
+            None => {
+                let haystack = Arc::new(self.build_haystack());
+                self.haystack = Some(haystack.clone());
+                self.item_scorer = self.scorer.as_ref().map(|scorer| scorer(&self.item_list));
+                haystack
+            }
+        };
+        let job = Job {
+            query: self.query.clone(),
+            haystack,
+            scorer: self.item_scorer.clone(),
+            candidates,
+            top_n: self.visible_height(),
+            ranking: self.ranking.clone(),
+            parallel: self.parallel(),
+        };
+        self.match_worker().submit(job);
+    }
+
+    /// Set if sort the matched items by score in query mode, otherwise they are kept in the
+    /// order they were added. Default is `true`.
+    pub fn set_sort(&mut self, b: bool) -> &mut Self {
+        self.ranking.sort = b;
+        self.invalidate_query_cache();
+        self
+    }
+
+    /// Set the criteria to order the matched items with the same score, the earlier ones take
+    /// precedence. Items that are still tied keep the order they were added.
+    ///
+    /// A criterion given more than once is only used where it first appears, so there are at
+    /// most 4 of them. By default there is no extra criteria.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Menu, Tiebreak};
+    /// let mut menu = Menu::<i32>::new().unwrap();
+    /// menu.set_tiebreak(&[Tiebreak::Length, Tiebreak::Begin]);
+    /// ```
+    pub fn set_tiebreak(&mut self, tiebreak: &[Tiebreak]) -> &mut Self {
+        self.ranking.tiebreak.clear();
+        for &criterion in tiebreak {
+            if !self.ranking.tiebreak.contains(&criterion) {
+                self.ranking.tiebreak.push(criterion);
+            }
+        }
+        self.invalidate_query_cache();
+        self
+    }
+
+    /// make sure the first `n` matched items are in their final order, which is required before
+    /// they're displayed
+    pub(crate) fn ensure_ranked(&mut self, n: usize) {
+        let total = self.matches.len();
+        let n = n.min(total);
+        let done = self.matches.ranked;
+        if n <= done {
+            return;
+        }
+        let haystack = match &self.haystack {
+            Some(haystack) => haystack.clone(),
+            None => return,
+        };
+
+        // rank at least as many as before, so scrolling through everything stays O(n log n)
+        let n = n.max(done * 2).min(total);
+        let ranking = &self.ranking;
+        // rank the cached result in place when it's the one displayed, so it's never copied and
+        // the ranking is kept if the query is restored from the cache
+        let cached = self
+            .query_cache
+            .get_mut(&self.query)
+            .filter(|result| Arc::ptr_eq(&result.matched, &self.matches.matched));
+        if let Some(result) = cached {
+            self.matches.matched = Arc::default();
+            let rest = &mut Arc::get_mut(&mut result.matched).unwrap()[done..];
+            result.ranked = done + rank_best(rest, n - done, |m| haystack.rank_key(m, ranking));
+            self.matches.matched = result.matched.clone();
+            self.matches.ranked = result.ranked;
+            return;
+        }
+        let rest = &mut Arc::make_mut(&mut self.matches.matched)[done..];
+        let ranked = rank_best(rest, n - done, |m| haystack.rank_key(m, ranking));
+        self.matches.ranked += ranked;
+    }
+
+    /// Set a function to compute the final score of a matched item from the score given by the
+    /// fuzzy matcher, matched items are ranked by the final scores.
+    ///
+    /// By default the final score is the matcher's score plus the [weight](Item::weight) of the
+    /// item. The custom scorer replaces it, so it has to add the weight itself if needed.
+    ///
+    /// The scorer runs on the match worker, on a copy of the items taken whenever the item list
+    /// changes.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Item, Menu};
+    /// let mut menu = Menu::<i32>::new().unwrap();
+    /// // prefer the items with a greater value
+    /// menu.set_scorer(|item: &Item<i32>, score| score + item.weight + item.value as i64);
+    /// ```
+    pub fn set_scorer<F>(&mut self, scorer: F) -> &mut Self
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Fn(&Item<T>, i64) -> i64 + Send + Sync + 'static,
+    {
+        let scorer = Arc::new(scorer);
+        self.scorer = Some(Box::new(move |items: &[Item<T>]| {
+            let items = items.to_vec();
+            let scorer = scorer.clone();
+            Arc::new(move |idx: usize, score| scorer(&items[idx], score))
+        }));
+        self.invalidate_query_cache();
+        self
+    }
+
+    /// Set if match the query against the [descriptions](Item::description) of the items too.
+    /// Default is `false`.
+    pub fn set_search_description(&mut self, b: bool) -> &mut Self {
+        self.search_description = b;
+        self.invalidate_query_cache();
+        self
+    }
+
+    /// Set if match large lists on multiple threads. Default is `true`.
+    pub fn enable_parallel_matching(&mut self, b: bool) -> &mut Self {
+        self.parallel_matching = b;
+        self
+    }
+
+    /// Set the number of items above which the list is matched in parallel. Default is `10000`.
+    pub fn set_parallel_threshold(&mut self, n: usize) -> &mut Self {
+        self.parallel_threshold = n;
+        self
+    }
+
+    /// Set how many items a thread matches at a time when matching in parallel. Default is `50`.
+    pub fn set_parallel_chunk_size(&mut self, n: usize) -> &mut Self {
+        self.parallel_chunk_size = n.max(1);
+        self
+    }
+
+    /// Set the number of threads of the thread pool used for parallel matching, default is the
+    /// number of CPUs.
+    ///
+    /// This replaces the pool set by [set_thread_pool](Self::set_thread_pool).
+    pub fn set_num_threads(&mut self, n: usize) -> &mut Self {
+        self.num_threads = Some(n);
+        self.rayon_pool = None;
+        self
+    }
+
+    /// Use the given thread pool for parallel matching instead of creating a new one, this is
+    /// useful when the application already has a rayon pool.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::sync::Arc;
+    /// let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+    /// let mut menu = termenu::Menu::<i32>::new().unwrap();
+    /// menu.set_thread_pool(pool);
+    /// ```
+    pub fn set_thread_pool(&mut self, pool: Arc<rayon::ThreadPool>) -> &mut Self {
+        self.rayon_pool = Some(pool);
+        self
+    }
+
+    /// whether a query is being matched in the background
+    pub(crate) fn is_matching(&self) -> bool {
+        self.pending_match.is_some()
+    }
+
+    /// index in `item_list` of the item under the cursor. while the query is matched again after
+    /// the item list changed, it's the item the cursor is going to be put on
+    pub(crate) fn cursor_item_idx(&self) -> Option<usize> {
+        match &self.pending_match {
+            Some(pending) if !pending.received && pending.keep_cursor_on.is_some() => {
+                pending.keep_cursor_on
+            }
+            _ => self.item_idx_at(self.cursor_idx()),
+        }
+    }
+
+    /// apply the latest progress of the background matching, return true if a redraw is needed
+    pub(crate) fn receive_matches(&mut self) -> bool {
+        let progress = match &self.match_worker {
+            Some(worker) => worker.try_recv(),
+            None => None,
+        };
+        match progress {
+            None => false,
+            Some(Progress::Partial(best)) => {
+                let ranked = best.len();
+                self.apply_matches(best.into(), ranked);
+                true
+            }
+            Some(Progress::Done(result)) => {
+                let size: usize = self.query_cache.values().map(QueryResult::size).sum();
+                if size + result.size() > QUERY_CACHE_CAPACITY {
+                    self.query_cache.clear();
                 }
+                let query = self.pending_match.as_ref().unwrap().query.clone();
+                self.query_cache.insert(query, result);
+                self.apply_cached_result();
+                true
             }
         }
+    }
 
-        // reverse sort by score
-        self.matched_item_indices.sort_by(|a, b| {
-            let a = self.item_list[*a].score;
-            let b = self.item_list[*b].score;
-            b.cmp(&a)
-        });
+    /// drop the cached query results and cancel the background matching, must be called
+    /// whenever the item list changes
+    pub(crate) fn invalidate_query_cache(&mut self) {
+        self.query_cache.clear();
+        self.haystack = None;
+        self.item_scorer = None;
+        self.cancel_matching();
     }
 
-    fn fuzzy_match_rayon(&mut self) {
-        use rayon::prelude::*;
-        use std::sync::Mutex;
+    /// stop the background matching, its result is dropped
+    pub(crate) fn cancel_matching(&mut self) {
+        self.pending_match = None;
+        if let Some(worker) = &self.match_worker {
+            worker.cancel();
+        }
+    }
 
-        let rayon_pool = self.rayon_pool.get_or_init(|| {
-            rayon::ThreadPoolBuilder::new()
-                .num_threads(num_cpus::get())
-                .build()
-                .unwrap()
-        });
+    pub(crate) fn match_worker(&mut self) -> &MatchWorker {
+        self.match_worker.get_or_insert_with(MatchWorker::spawn)
+    }
 
-        // reset
-        let matched_item_indices = Mutex::new(Vec::new());
-        self.selection_idx = 0;
-        self.scroll_offset = 0;
-
-        // match
-        let matcher = self.fuzzy_matcher.get_or_init(SkimMatcherV2::default);
-        let chunk_size = 50;
-        rayon_pool.install(|| {
-            self.item_list
-                .par_chunks_mut(chunk_size)
-                .enumerate()
-                .for_each(|(i, chunk)| {
-                    let base_idx = i * chunk_size;
-                    chunk.iter_mut().enumerate().for_each(|(i, item)| {
-                        match matcher.fuzzy_indices(&item.alias, &self.query) {
-                            None => (),
-                            Some((score, indices)) => {
-                                if score <= 0 {
-                                    return;
-                                }
-                                item.score = Some(score);
-                                item.matched_indices = Some(indices);
-                                matched_item_indices.lock().unwrap().push(i + base_idx);
-                            }
-                        }
-                    });
-                });
+    fn build_haystack(&self) -> Haystack {
+        let texts = self
+            .item_list
+            .iter()
+            .map(|item| match &item.description {
+                Some(description) if self.search_description => {
+                    format!("{} {}", item.search_text(), description)
+                }
+                _ => item.search_text().into_owned(),
+            })
+            .collect();
+        let alias_lens = self
+            .item_list
+            .iter()
+            .map(|item| item.alias.chars().count())
+            .collect();
+        // the custom scorer is given the raw scores
+        let weighted = self.scorer.is_none() && self.item_list.iter().any(|item| item.weight != 0);
+        let weights = weighted.then(|| self.item_list.iter().map(|item| item.weight).collect());
+        let grouped = self.item_list.iter().any(|item| item.group.is_some());
+        let groups = grouped.then(|| {
+            let mut group = 0;
+            let mut groups = Vec::with_capacity(self.item_list.len());
+            for (i, item) in self.item_list.iter().enumerate() {
+                if i > 0 && item.group != self.item_list[i - 1].group {
+                    group += 1;
+                }
+                groups.push(group);
+            }
+            groups
         });
+        Haystack {
+            texts,
+            alias_lens,
+            weights,
+            groups,
+        }
+    }
+
+    // the parallel matching settings for the current item list, `None` if it should be matched
+    // sequentially
+    fn parallel(&mut self) -> Option<Parallel> {
+        if !self.parallel_matching || self.item_list.len() <= self.parallel_threshold {
+            return None;
+        }
+        if self.rayon_pool.is_none() {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.num_threads.unwrap_or_else(num_cpus::get))
+                .build()
+                .ok()?;
+            self.rayon_pool = Some(Arc::new(pool));
+        }
+        Some(Parallel {
+            pool: self.rayon_pool.clone()?,
+            threshold: self.parallel_threshold,
+            chunk_size: self.parallel_chunk_size,
+        })
+    }
+
+    fn apply_cached_result(&mut self) {
+        let query = &self.pending_match.as_ref().unwrap().query;
+        let result = &self.query_cache[query];
+        let (matched, ranked) = (result.matched.clone(), result.ranked);
+        self.apply_matches(matched, ranked);
+        self.pending_match = None;
+    }
+
+    fn apply_matches(&mut self, matched: Arc<[Matched]>, ranked: usize) {
+        self.matches = MatchState { matched, ranked };
+
+        let pending = self.pending_match.as_mut().unwrap();
+        let first = !pending.received;
+        pending.received = true;
+        let keep_cursor_on = pending.keep_cursor_on;
+        if keep_cursor_on.is_some() {
+            // the position is only meaningful once everything is in its final order
+            self.ensure_ranked(self.matches.len());
+        }
+        let pos = keep_cursor_on.and_then(|c| self.matches.position(c));
+        match pos {
+            Some(pos) => {
+                let _ = self.move_to(pos);
+            }
+            None if first => {
+                self.selection_idx = 0;
+                self.scroll_offset = 0;
+                let _ = self.move_to(0);
+            }
+            // the cursor may have been moved since the first progress was applied, keep it
+            None => {
+                let _ = self.move_to(self.cursor_idx());
+            }
+        }
+    }
+}
+
+// items are ranked by ascending order of their keys, the last element is the item index
+type RankKey = (usize, Reverse<i64>, [usize; 4], usize);
+
+pub(crate) fn rank_key(
+    group: usize,
+    idx: usize,
+    score: i64,
+    indices: &[usize],
+    alias_lens: &[usize],
+    ranking: &Ranking,
+) -> RankKey {
+    if !ranking.sort {
+        return (group, Reverse(0), [0; 4], idx);
+    }
+    let mut tiebreak = [0; 4];
+    for (key, criterion) in tiebreak.iter_mut().zip(ranking.tiebreak.iter()) {
+        *key = match criterion {
+            Tiebreak::Length => alias_lens[idx],
+            Tiebreak::Begin => indices.first().copied().unwrap_or(0),
+            Tiebreak::End => {
+                let last = indices.last().copied().unwrap_or(0);
+                alias_lens[idx].saturating_sub(last + 1)
+            }
+            Tiebreak::Index => idx,
+        };
+    }
+    (group, Reverse(score), tiebreak, idx)
+}
+
+/// move the best `n` items to the front in ranked order and leave the rest unordered, which is
+/// much cheaper than sorting everything when only a screenful is displayed.
+///
+/// return how many leading items are in their final order
+pub(crate) fn rank_best<E, K: Ord>(items: &mut [E], n: usize, key: impl Fn(&E) -> K) -> usize {
+    if n == 0 {
+        return 0;
+    }
+    if n < items.len() {
+        items.select_nth_unstable_by_key(n, &key);
+        items[..n].sort_unstable_by_key(&key);
+        return n;
+    }
+    items.sort_unstable_by_key(key);
+    items.len()
+}
+
+/// the `n` best of the matched items, ordered by the given ranking
+pub(crate) fn rank_top_n(
+    matched: &[Matched],
+    n: usize,
+    haystack: &Haystack,
+    ranking: &Ranking,
+) -> Vec<Matched> {
+    let mut best: Vec<&Matched> = matched.iter().collect();
+    let ranked = rank_best(&mut best, n, |m| haystack.rank_key(m, ranking));
+    best.truncate(ranked);
+    best.into_iter().cloned().collect()
+}
+
+/// match the query against the given items, `candidates` must be in ascending order. they're
+/// matched on the thread pool if `parallel` is given, on the current thread otherwise.
+///
+/// return the items that contain the query as a subsequence in the same order, including the
+/// ones whose score is not positive
+pub(crate) fn match_candidates(
+    matcher: &SkimMatcherV2,
+    parallel: Option<&Parallel>,
+    query: &str,
+    haystack: &[String],
+    candidates: &[usize],
+) -> Vec<Matched> {
+    if let Some(parallel) = parallel {
+        return match_candidates_rayon(matcher, parallel, query, haystack, candidates);
+    }
+
+    let mut matched = Vec::new();
+    for &i in candidates {
+        if let Some((score, indices)) = matcher.fuzzy_indices(&haystack[i], query) {
+            matched.push((i, score, indices));
+        }
+    }
+    matched
+}
+
+fn match_candidates_rayon(
+    matcher: &SkimMatcherV2,
+    parallel: &Parallel,
+    query: &str,
+    haystack: &[String],
+    candidates: &[usize],
+) -> Vec<Matched> {
+    use rayon::prelude::*;
+
+    // each chunk is matched on its own, and the ordered collect keeps the input order without
+    // sharing anything between threads
+    parallel.pool.install(|| {
+        candidates
+            .par_chunks(parallel.chunk_size.max(1))
+            .flat_map_iter(|chunk| {
+                chunk.iter().filter_map(|&i| {
+                    let (score, indices) = matcher.fuzzy_indices(&haystack[i], query)?;
+                    Some((i, score, indices))
+                })
+            })
+            .collect()
+    })
+}
+
+/// match all the items and rank the best `n` the same way the match worker does, return their
+/// indices. only exposed for the benchmarks
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub fn bench_match(
+    pool: Arc<rayon::ThreadPool>,
+    query: &str,
+    haystack: &[String],
+    n: usize,
+) -> Vec<usize> {
+    let matcher = SkimMatcherV2::default();
+    let candidates: Vec<usize> = (0..haystack.len()).collect();
+    let ranking = Ranking::default();
+    let parallel = Parallel {
+        pool,
+        threshold: DEFAULT_PARALLEL_THRESHOLD,
+        chunk_size: DEFAULT_PARALLEL_CHUNK_SIZE,
+    };
+    let mut matched = match_candidates(&matcher, Some(&parallel), query, haystack, &candidates);
+    matched.retain(|(_, score, _)| *score > 0);
+    // there is no tiebreak, so the alias lengths are never read
+    let ranked = rank_best(&mut matched, n, |m| {
+        rank_key(0, m.0, m.1, &m.2, &[], &ranking)
+    });
+    matched[..ranked].iter().map(|(idx, _, _)| *idx).collect()
+}
+
+/// rank the best `n` of the matched `(item index, score, matched char indices)` the same way the
+/// match worker does, return how many are ranked. only exposed for the benchmarks
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub fn bench_rank(matched: &mut [(usize, i64, Vec<usize>)], n: usize) -> usize {
+    let ranking = Ranking::default();
+    // there is no tiebreak, so the alias lengths are never read
+    rank_best(matched, n, |m| rank_key(0, m.0, m.1, &m.2, &[], &ranking))
+}
+
+/// check if every char of `sub` appears in `s` in the same order
+fn is_subsequence(sub: &str, s: &str) -> bool {
+    let mut chars = s.chars();
+    sub.chars().all(|c| chars.any(|x| x == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use super::{rank_best, rank_top_n, Haystack, Ranking};
+    use crate::{Item, Menu, Mode, Tiebreak};
+
+    fn menu(parallel_threshold: usize) -> Menu<usize> {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        let words = ["alpha", "beta", "gamma", "delta", "kappa", "lambda", "zeta"];
+        let items = (0..5000)
+            .map(|i| {
+                let alias = format!("{}_{}_{}", words[i % 7], words[i * 3 % 7], i);
+                Item::new(&alias, i)
+            })
+            .collect();
+        menu.add_list(items)
+            .set_parallel_threshold(parallel_threshold);
+        menu.mode = Mode::Query;
+        menu
+    }
+
+    // match the query to the end and return every match in ranked order
+    fn matches(menu: &mut Menu<usize>, query: &str) -> Vec<(usize, i64, Vec<usize>)> {
+        menu.query = query.to_string();
+        menu.fuzzy_match();
+        while menu.is_matching() {
+            menu.receive_matches();
+            std::thread::yield_now();
+        }
+        menu.ensure_ranked(menu.matches.len());
+        menu.matches.matched.to_vec()
+    }
+
+    #[test]
+    fn narrowed_matching_equals_full_rescan() {
+        for threshold in [usize::MAX, 100] {
+            let mut narrowed = menu(threshold);
+            let mut full = menu(threshold);
+            for query in ["a", "al", "al_", "al_e", "al_et", "a", "ka", "kap_1", "z9"] {
+                let expected = matches(&mut full, query);
+                full.invalidate_query_cache();
+                assert_eq!(matches(&mut narrowed, query), expected, "query {query:?}");
+            }
+        }
+    }
+
+    // scores with many duplicates, so the tiebreaks matter too
+    fn scattered_matches() -> Vec<(usize, i64, Vec<usize>)> {
+        (0..100)
+            .map(|i| (i, (i * 37 % 11) as i64, vec![i % 5, i % 5 + 1]))
+            .collect()
+    }
+
+    #[test]
+    fn top_n_equals_a_full_sort() {
+        let matched = scattered_matches();
+        let ranking = Ranking {
+            sort: true,
+            tiebreak: vec![Tiebreak::Length, Tiebreak::Begin],
+        };
+        let haystack = Haystack {
+            texts: Vec::new(),
+            alias_lens: (0..100).map(|i| i % 7).collect(),
+            weights: None,
+            groups: None,
+        };
+        let mut sorted = matched.clone();
+        sorted.sort_by_key(|m| haystack.rank_key(m, &ranking));
+
+        for n in [0, 1, 10, 99, 100, 101, usize::MAX] {
+            let best = rank_top_n(&matched, n, &haystack, &ranking);
+            assert_eq!(best, sorted[..n.min(sorted.len())], "n = {n}");
+        }
+    }
+
+    #[test]
+    fn rank_best_orders_only_the_best_n() {
+        for n in [0, 1, 50, 99, 100, 101] {
+            let mut matched = scattered_matches();
+            let ranked = rank_best(&mut matched, n, |m| (Reverse(m.1), m.0));
+            assert_eq!(ranked, n.min(100), "n = {n}");
+
+            let mut sorted = scattered_matches();
+            sorted.sort_by_key(|m| (Reverse(m.1), m.0));
+            assert_eq!(matched[..ranked], sorted[..ranked], "n = {n}");
+            // the rest are all ranked after them
+            let mut rest: Vec<_> = matched[ranked..].iter().map(|m| m.0).collect();
+            rest.sort_unstable();
+            let mut expected: Vec<_> = sorted[ranked..].iter().map(|m| m.0).collect();
+            expected.sort_unstable();
+            assert_eq!(rest, expected, "n = {n}");
+        }
+    }
+
+    #[test]
+    fn cached_result_is_shared() {
+        let mut menu = menu(usize::MAX);
+        matches(&mut menu, "al");
+        matches(&mut menu, "alp");
+        menu.query = "al".to_string();
+        menu.fuzzy_match();
+        assert!(!menu.is_matching());
+        assert!(std::sync::Arc::ptr_eq(
+            &menu.query_cache["al"].matched,
+            &menu.matches.matched
+        ));
+    }
+
+    #[test]
+    fn weights_and_scorers_change_the_order() {
+        let items = || {
+            vec![
+                Item::new("ab", 0),
+                Item::new("a_b", 1),
+                Item::new("a__b", 2).with_weight(1000),
+            ]
+        };
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(items());
+        menu.mode = Mode::Query;
+        let ranked = |menu: &mut Menu<usize>| {
+            let matched = matches(menu, "ab");
+            matched.iter().map(|m| m.0).collect::<Vec<_>>()
+        };
+        assert_eq!(ranked(&mut menu), [2, 0, 1]);
+
+        // the scorer replaces the weights
+        menu.set_scorer(|item: &Item<usize>, score| score + 1000 * item.value as i64);
+        assert_eq!(ranked(&mut menu), [2, 1, 0]);
+        menu.set_scorer(|item: &Item<usize>, score| score - 1000 * item.value as i64);
+        assert_eq!(ranked(&mut menu), [0, 1, 2]);
+    }
+
+    #[test]
+    fn partial_results_are_scored() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        // the worst match of all, in the first of several batches
+        let mut items = vec![Item::new("a______b", 0)];
+        items.extend((1..50000).map(|i| Item::new(&format!("ab_{i}"), i)));
+        menu.add_list(items)
+            .set_scorer(|item: &Item<usize>, score| match item.value {
+                0 => score + 1000,
+                _ => score,
+            });
+        menu.mode = Mode::Query;
+        menu.query = "ab".to_string();
+        menu.fuzzy_match();
+        while menu.is_matching() {
+            if menu.receive_matches() {
+                assert_eq!(menu.matches.item_idx(0), Some(0));
+            }
+            std::thread::yield_now();
+        }
+        assert_eq!(menu.matches.item_idx(0), Some(0));
+    }
+
+    #[test]
+    fn tiebreaks_order_equal_scores() {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add_list(vec![
+            Item::new("xxab", 0),
+            Item::new("abx", 1),
+            Item::new("ab", 2).with_keywords(&["long", "keywords"]),
+            Item::new("xab", 3),
+        ])
+        // every match scores the same, so only the tiebreaks order them
+        .set_scorer(|_: &Item<usize>, _| 0);
+        menu.mode = Mode::Query;
+        let mut ranked = |tiebreak: &[Tiebreak]| {
+            menu.set_tiebreak(tiebreak);
+            let matched = matches(&mut menu, "ab");
+            matched.iter().map(|m| m.0).collect::<Vec<_>>()
+        };
+
+        assert_eq!(ranked(&[]), [0, 1, 2, 3]);
+        // the lengths are the aliases' ones, not the search texts'
+        assert_eq!(ranked(&[Tiebreak::Length]), [2, 1, 3, 0]);
+        assert_eq!(ranked(&[Tiebreak::Begin]), [1, 2, 3, 0]);
+        assert_eq!(ranked(&[Tiebreak::End]), [0, 2, 3, 1]);
+        assert_eq!(ranked(&[Tiebreak::End, Tiebreak::Length]), [2, 3, 0, 1]);
+        assert_eq!(ranked(&[Tiebreak::Index]), [0, 1, 2, 3]);
 
-        self.matched_item_indices = matched_item_indices.into_inner().unwrap();
+        let repeated = [
+            Tiebreak::Length,
+            Tiebreak::Length,
+            Tiebreak::Begin,
+            Tiebreak::Length,
+            Tiebreak::Index,
+        ];
+        assert_eq!(ranked(&repeated), [2, 1, 3, 0]);
+        assert_eq!(
+            menu.ranking.tiebreak,
+            [Tiebreak::Length, Tiebreak::Begin, Tiebreak::Index]
+        );
     }
 }
@@ -0,0 +1,361 @@
+use std::io;
+
+use crate::{keymap::KeyResponse, Item, Menu, Mode};
+
+/// structure of the item list when some items have children
+pub(crate) struct Tree {
+    // parent of each item, indexed by item index
+    parents: Vec<Option<usize>>,
+    // item indices of the rows displayed in normal mode, the children of collapsed items are
+    // hidden
+    pub(crate) rows: Vec<usize>,
+}
+
+impl<T> Item<T> {
+    /// Set the children of the item, which makes the menu a tree.
+    ///
+    /// Children are hidden until their parent is expanded with `l`/Right (or Enter) and hidden
+    /// again with `h`/Left. Enter only selects leaves. In query mode, all the items are matched
+    /// and displayed with the path of their ancestors.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use termenu::{Item, Menu};
+    /// let editor = Item::new("editor", "editor").with_children(vec![
+    ///     Item::new("font", "editor.font"),
+    ///     Item::new("tab size", "editor.tab_size"),
+    /// ]);
+    /// let mut menu = Menu::new().unwrap();
+    /// let key = menu.add(editor).select().unwrap();
+    /// ```
+    pub fn with_children(mut self, children: Vec<Item<T>>) -> Item<T> {
+        self.children = children;
+        self
+    }
+
+    /// Depth of the item in the tree, top level items are at depth 0
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+/// move the item and its descendants into `out` in pre-order, so a subtree is a contiguous
+/// range of the list. each of them is given a new id from `next_id`
+pub(crate) fn flatten<T>(item: Item<T>, next_id: &mut u64, out: &mut Vec<Item<T>>) {
+    flatten_at(item, 0, next_id, out);
+}
+
+fn flatten_at<T>(mut item: Item<T>, depth: usize, next_id: &mut u64, out: &mut Vec<Item<T>>) {
+    let children = std::mem::take(&mut item.children);
+    item.depth = depth;
+    item.id = *next_id;
+    *next_id += 1;
+    out.push(item);
+    for child in children {
+        flatten_at(child, depth + 1, next_id, out);
+    }
+}
+
+/// the reverse of `flatten`, put the descendants of the first item back into its children
+pub(crate) fn unflatten<T>(items: Vec<Item<T>>) -> Item<T> {
+    let mut items = items.into_iter().peekable();
+    let mut root = items.next().expect("a subtree is never empty");
+    nest(&mut root, &mut items);
+    root.depth = 0;
+    root
+}
+
+// move the following items that are deeper than the parent into its children
+fn nest<T>(parent: &mut Item<T>, items: &mut std::iter::Peekable<impl Iterator<Item = Item<T>>>) {
+    while let Some(mut child) = items.next_if(|item| item.depth > parent.depth) {
+        nest(&mut child, items);
+        child.depth = 0;
+        parent.children.push(child);
+    }
+}
+
+// tree navigation
+impl<T> Menu<T> {
+    /// rebuild the tree after the item list or the expanded items changed
+    pub(crate) fn refresh_tree(&mut self) {
+        if self.item_list.iter().all(|item| item.depth == 0) {
+            self.tree = None;
+            return;
+        }
+
+        // the parent of an item is the closest item above it with a lower depth
+        let mut parents = Vec::with_capacity(self.item_list.len());
+        let mut ancestors: Vec<usize> = Vec::new();
+        for (idx, item) in self.item_list.iter().enumerate() {
+            while let Some(&last) = ancestors.last() {
+                if self.item_list[last].depth < item.depth {
+                    break;
+                }
+                ancestors.pop();
+            }
+            parents.push(ancestors.last().copied());
+            ancestors.push(idx);
+        }
+
+        let mut rows = Vec::new();
+        let mut idx = 0;
+        while idx < self.item_list.len() {
+            rows.push(idx);
+            if self.has_children(idx) && !self.is_expanded(idx) {
+                idx = self.subtree_end(idx);
+            } else {
+                idx += 1;
+            }
+        }
+
+        self.tree = Some(Tree { parents, rows });
+    }
+
+    /// whether the item has children
+    pub(crate) fn has_children(&self, idx: usize) -> bool {
+        let depth = self.item_list[idx].depth;
+        self.item_list
+            .get(idx + 1)
+            .is_some_and(|next| next.depth > depth)
+    }
+
+    pub(crate) fn is_expanded(&self, idx: usize) -> bool {
+        self.expanded.contains(&self.item_list[idx].id)
+    }
+
+    /// aliases of the ancestors of the item, from the root down
+    pub(crate) fn ancestor_path(&self, idx: usize) -> Vec<&str> {
+        let mut path = Vec::new();
+        let mut parent = self.tree.as_ref().and_then(|tree| tree.parents[idx]);
+        while let Some(p) = parent {
+            path.push(self.item_list[p].alias.as_str());
+            parent = self.tree.as_ref().and_then(|tree| tree.parents[p]);
+        }
+        path.reverse();
+        path
+    }
+
+    /// expand the item under the cursor, or move to its first child if it's expanded already
+    pub(crate) fn key_expand(&mut self) -> io::Result<KeyResponse> {
+        let idx = match self.item_idx_at(self.cursor_idx()) {
+            Some(idx) if self.has_children(idx) => idx,
+            _ => return Ok(KeyResponse::new(false, false)),
+        };
+        if self.is_expanded(idx) {
+            return self.move_to(self.cursor_idx() + 1);
+        }
+        self.expanded.insert(self.item_list[idx].id);
+        self.refresh_tree();
+        Ok(KeyResponse::new(false, true))
+    }
+
+    /// collapse the item under the cursor, or move to its parent if it's collapsed already
+    pub(crate) fn key_collapse(&mut self) -> io::Result<KeyResponse> {
+        let idx = match self.item_idx_at(self.cursor_idx()) {
+            Some(idx) => idx,
+            None => return Ok(KeyResponse::new(false, false)),
+        };
+        if self.is_expanded(idx) {
+            self.expanded.remove(&self.item_list[idx].id);
+            self.refresh_tree();
+            return Ok(KeyResponse::new(false, true));
+        }
+        let parent = self.tree.as_ref().and_then(|tree| tree.parents[idx]);
+        match parent.and_then(|p| self.row_of(p)) {
+            Some(row) => self.move_to(row),
+            None => Ok(KeyResponse::new(false, false)),
+        }
+    }
+
+    /// Enter on an item with children, it's toggled in normal mode. In query mode the query is
+    /// dropped and the item is revealed and expanded in the tree
+    pub(crate) fn key_enter_branch(&mut self, idx: usize) -> io::Result<KeyResponse> {
+        match self.mode {
+            Mode::Normal => {
+                let id = self.item_list[idx].id;
+                if !self.expanded.remove(&id) {
+                    self.expanded.insert(id);
+                }
+            }
+            Mode::Query => {
+                self.expanded.insert(self.item_list[idx].id);
+                self.reveal(idx);
+                self.enter_normal_mode()?;
+            }
+        }
+        self.refresh_tree();
+        if let Some(row) = self.row_of(idx) {
+            self.move_to(row)?;
+        }
+        Ok(KeyResponse::new(false, true))
+    }
+
+    /// expand the ancestors of the item, so it's displayed in normal mode
+    pub(crate) fn reveal(&mut self, idx: usize) {
+        let mut parent = self.tree.as_ref().and_then(|tree| tree.parents[idx]);
+        while let Some(p) = parent {
+            self.expanded.insert(self.item_list[p].id);
+            parent = self.tree.as_ref().and_then(|tree| tree.parents[p]);
+        }
+        self.refresh_tree();
+    }
+
+    // index of the item right after the subtree of the given item
+    pub(crate) fn subtree_end(&self, idx: usize) -> usize {
+        let depth = self.item_list[idx].depth;
+        self.item_list[idx + 1..]
+            .iter()
+            .position(|item| item.depth <= depth)
+            .map_or(self.item_list.len(), |offset| idx + 1 + offset)
+    }
+
+    /// number of rows displayed in normal mode, the children of collapsed items are hidden
+    pub(crate) fn row_count(&self) -> usize {
+        match &self.tree {
+            Some(tree) => tree.rows.len(),
+            None => self.item_list.len(),
+        }
+    }
+
+    /// the normal mode row the item is displayed on, if it's visible
+    pub(crate) fn row_of(&self, idx: usize) -> Option<usize> {
+        match &self.tree {
+            Some(tree) => tree.rows.binary_search(&idx).ok(),
+            None => Some(idx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::{Item, Menu};
+
+    fn press(menu: &mut Menu<&'static str>, code: KeyCode) {
+        let resp = menu.dispatch_key(KeyEvent::new(code, KeyModifiers::NONE));
+        resp.unwrap();
+    }
+
+    fn rows(menu: &Menu<&'static str>) -> Vec<&'static str> {
+        (0..menu.item_count())
+            .map(|row| menu.item_at(row).unwrap().value)
+            .collect()
+    }
+
+    fn current(menu: &Menu<&'static str>) -> &'static str {
+        menu.item_at(menu.cursor_idx()).unwrap().value
+    }
+
+    fn settings() -> Item<&'static str> {
+        Item::new("settings", "settings").with_children(vec![
+            Item::new("editor", "editor").with_children(vec![
+                Item::new("font", "font"),
+                Item::new("tab size", "tab size"),
+            ]),
+            Item::new("theme", "theme"),
+        ])
+    }
+
+    fn tree_menu() -> Menu<&'static str> {
+        let mut menu = Menu::with_size(false, 0, 80, 24);
+        menu.add(settings()).add(Item::new("quit", "quit"));
+        menu.refresh_tree();
+        menu
+    }
+
+    #[test]
+    fn children_are_flattened_in_pre_order() {
+        let menu = tree_menu();
+        let flattened: Vec<_> = menu
+            .item_list
+            .iter()
+            .map(|item| (item.value, item.depth()))
+            .collect();
+        assert_eq!(
+            flattened,
+            [
+                ("settings", 0),
+                ("editor", 1),
+                ("font", 2),
+                ("tab size", 2),
+                ("theme", 1),
+                ("quit", 0),
+            ]
+        );
+        assert_eq!(menu.ancestor_path(3), ["settings", "editor"]);
+        assert_eq!(rows(&menu), ["settings", "quit"]);
+    }
+
+    #[test]
+    fn expand_and_collapse() {
+        let mut menu = tree_menu();
+        press(&mut menu, KeyCode::Char('l'));
+        assert_eq!(rows(&menu), ["settings", "editor", "theme", "quit"]);
+        // moves into the expanded item
+        press(&mut menu, KeyCode::Char('l'));
+        assert_eq!(current(&menu), "editor");
+        press(&mut menu, KeyCode::Right);
+        assert_eq!(rows(&menu).len(), 6);
+
+        press(&mut menu, KeyCode::Left);
+        assert_eq!(rows(&menu), ["settings", "editor", "theme", "quit"]);
+        // moves to the parent of the collapsed item
+        press(&mut menu, KeyCode::Char('h'));
+        assert_eq!(current(&menu), "settings");
+        press(&mut menu, KeyCode::Char('h'));
+        assert_eq!(rows(&menu), ["settings", "quit"]);
+    }
+
+    #[test]
+    fn expanded_items_survive_list_changes() {
+        let mut menu = tree_menu();
+        press(&mut menu, KeyCode::Char('l'));
+        // shifts the indices of all the items
+        menu.add_group("recent", vec![Item::new("notes", "notes")]);
+        menu.refresh_tree();
+        assert_eq!(
+            rows(&menu),
+            ["settings", "editor", "theme", "quit", "notes"]
+        );
+        menu.remove_while_open(|_, item| item.value == "quit");
+        assert_eq!(rows(&menu), ["settings", "editor", "theme", "notes"]);
+    }
+
+    #[test]
+    fn enter_selects_leaves_only() {
+        let mut menu = tree_menu();
+        press(&mut menu, KeyCode::Enter);
+        assert!(!menu.selected);
+        assert_eq!(rows(&menu), ["settings", "editor", "theme", "quit"]);
+
+        press(&mut menu, KeyCode::Down);
+        press(&mut menu, KeyCode::Down);
+        press(&mut menu, KeyCode::Enter);
+        assert!(menu.selected);
+        assert_eq!(current(&menu), "theme");
+    }
+
+    #[test]
+    fn taking_a_branch_takes_its_children() {
+        let mut menu = tree_menu();
+        press(&mut menu, KeyCode::Char('l'));
+        press(&mut menu, KeyCode::Char('l'));
+        menu.selection = menu.item_idx_at(menu.cursor_idx());
+
+        let editor = menu.take_selection().unwrap();
+        assert_eq!(editor.value, "editor");
+        assert_eq!(editor.depth(), 0);
+        let children: Vec<_> = editor.children.iter().map(|item| item.value).collect();
+        assert_eq!(children, ["font", "tab size"]);
+        assert_eq!(rows(&menu), ["settings", "theme", "quit"]);
+
+        // it can be added back as it was
+        menu.add(editor);
+        let values: Vec<_> = menu.item_list.iter().map(|item| item.value).collect();
+        assert_eq!(
+            values,
+            ["settings", "theme", "quit", "editor", "font", "tab size"]
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::query::{self, Haystack, ItemScorer, Matched, Parallel, QueryResult, Ranking};
+
+// number of candidates matched between two progress reports
+const MATCH_BATCH_SIZE: usize = 20000;
+
+/// a query to match in the background
+pub(crate) struct Job {
+    pub(crate) query: String,
+    pub(crate) haystack: Arc<Haystack>,
+    // the custom scorer, it replaces the weights
+    pub(crate) scorer: Option<Arc<ItemScorer>>,
+    // item indices to match in ascending order, `None` means all the items
+    pub(crate) candidates: Option<Arc<[usize]>>,
+    // how many of the best matches to report while the job is still running
+    pub(crate) top_n: usize,
+    pub(crate) ranking: Ranking,
+    // `None` if the job should be matched sequentially
+    pub(crate) parallel: Option<Parallel>,
+}
+
+impl Job {
+    // decided for the whole job, since a batch alone may be smaller than the threshold
+    fn parallel_for(&self, candidate_cnt: usize) -> Option<&Parallel> {
+        self.parallel
+            .as_ref()
+            .filter(|p| candidate_cnt > p.threshold)
+    }
+}
+
+pub(crate) enum Progress {
+    // best matches found so far, ranked
+    Partial(Vec<Matched>),
+    Done(QueryResult),
+}
+
+struct Shared {
+    // generation of the latest job, a job is cancelled as soon as it's not the latest one
+    generation: AtomicU64,
+    // latest progress of the latest job, older progress is overwritten since only the latest
+    // one is worth drawing
+    published: Mutex<Option<(u64, Progress)>>,
+    // waker of the async event loop waiting for progress
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    fn is_cancelled(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::Acquire) != generation
+    }
+
+    fn publish(&self, generation: u64, progress: Progress) {
+        if self.is_cancelled(generation) {
+            return;
+        }
+        *self.published.lock().unwrap() = Some((generation, progress));
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// matches queries on a background thread, so the event loop never waits for matching
+pub(crate) struct MatchWorker {
+    jobs: mpsc::Sender<(u64, Job)>,
+    shared: Arc<Shared>,
+}
+
+impl MatchWorker {
+    pub(crate) fn spawn() -> Self {
+        let (jobs, rx) = mpsc::channel::<(u64, Job)>();
+        let shared = Arc::new(Shared {
+            generation: AtomicU64::new(0),
+            published: Mutex::new(None),
+            waker: Mutex::new(None),
+        });
+
+        let worker_shared = shared.clone();
+        thread::spawn(move || {
+            let matcher = SkimMatcherV2::default();
+            // exits when the worker is dropped
+            while let Ok(mut job) = rx.recv() {
+                // skip the jobs that have been superseded already
+                while let Ok(newer) = rx.try_recv() {
+                    job = newer;
+                }
+                let (generation, job) = job;
+                run_job(&worker_shared, &matcher, generation, job);
+            }
+        });
+
+        MatchWorker { jobs, shared }
+    }
+
+    /// start matching a query, the running job (if any) is cancelled
+    pub(crate) fn submit(&self, job: Job) {
+        let generation = self.shared.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        let _ = self.jobs.send((generation, job));
+    }
+
+    /// cancel the running job (if any), its progress will never be received
+    pub(crate) fn cancel(&self) {
+        self.shared.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// take the latest progress of the latest job, if any
+    pub(crate) fn try_recv(&self) -> Option<Progress> {
+        let (generation, progress) = self.shared.published.lock().unwrap().take()?;
+        if self.shared.is_cancelled(generation) {
+            return None;
+        }
+        Some(progress)
+    }
+
+    /// a handle that can be awaited until there is some progress to receive
+    pub(crate) fn signal(&self) -> ProgressSignal {
+        ProgressSignal(self.shared.clone())
+    }
+}
+
+impl Drop for MatchWorker {
+    fn drop(&mut self) {
+        // stop the running job, the thread exits once the job channel is closed
+        self.cancel();
+    }
+}
+
+pub(crate) struct ProgressSignal(Arc<Shared>);
+
+impl ProgressSignal {
+    pub(crate) fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        // register first, so progress published after the check below still wakes us up
+        *self.0.waker.lock().unwrap() = Some(cx.waker().clone());
+        if self.0.published.lock().unwrap().is_some() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+fn run_job(shared: &Shared, matcher: &SkimMatcherV2, generation: u64, job: Job) {
+    let all: Vec<usize>;
+    let source = match &job.candidates {
+        Some(candidates) => &candidates[..],
+        None => {
+            all = (0..job.haystack.texts.len()).collect();
+            &all
+        }
+    };
+
+    let texts = &job.haystack.texts;
+    let weights = job.haystack.weights.as_deref();
+    let mut candidates = Vec::new();
+    let mut matched = Vec::new();
+    let parallel = job.parallel_for(source.len());
+    let batch_cnt = source.len().div_ceil(MATCH_BATCH_SIZE);
+    for (i, batch) in source.chunks(MATCH_BATCH_SIZE).enumerate() {
+        if shared.is_cancelled(generation) {
+            return;
+        }
+
+        for (idx, score, indices) in
+            query::match_candidates(matcher, parallel, &job.query, texts, batch)
+        {
+            candidates.push(idx);
+            // the weight only changes the rank, an item matches as long as the query does
+            if score > 0 {
+                let score = match &job.scorer {
+                    Some(scorer) => scorer(idx, score),
+                    None => score + weights.map_or(0, |w| w[idx]),
+                };
+                matched.push((idx, score, indices));
+            }
+        }
+
+        if i + 1 < batch_cnt {
+            let best = query::rank_top_n(&matched, job.top_n, &job.haystack, &job.ranking);
+            shared.publish(generation, Progress::Partial(best));
+        }
+    }
+
+    // only the visible ones are ranked now, the rest is ranked lazily when scrolling
+    let ranked = query::rank_best(&mut matched, job.top_n, |m| {
+        job.haystack.rank_key(m, &job.ranking)
+    });
+    let result = QueryResult {
+        candidates: candidates.into(),
+        matched: matched.into(),
+        ranked,
+    };
+    shared.publish(generation, Progress::Done(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn job(parallel_threshold: Option<usize>) -> Job {
+        let texts: Vec<String> = (0..MATCH_BATCH_SIZE * 3 / 2)
+            .map(|i| format!("item_{i}"))
+            .collect();
+        let parallel = parallel_threshold.map(|threshold| Parallel {
+            pool: Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(2)
+                    .build()
+                    .unwrap(),
+            ),
+            threshold,
+            chunk_size: query::DEFAULT_PARALLEL_CHUNK_SIZE,
+        });
+        Job {
+            query: "m_12".to_string(),
+            haystack: Arc::new(Haystack {
+                alias_lens: texts.iter().map(|t| t.chars().count()).collect(),
+                texts,
+                weights: None,
+                groups: None,
+            }),
+            scorer: None,
+            candidates: None,
+            top_n: 10,
+            ranking: Ranking::default(),
+            parallel,
+        }
+    }
+
+    fn run(job: Job) -> QueryResult {
+        let shared = Shared {
+            generation: AtomicU64::new(1),
+            published: Mutex::new(None),
+            waker: Mutex::new(None),
+        };
+        run_job(&shared, &SkimMatcherV2::default(), 1, job);
+        let published = shared.published.lock().unwrap().take();
+        match published {
+            Some((_, Progress::Done(result))) => result,
+            _ => panic!("the job is not done"),
+        }
+    }
+
+    #[test]
+    fn threshold_above_the_batch_size_still_matches_in_parallel() {
+        let threshold = MATCH_BATCH_SIZE + 1000;
+        let parallel = job(Some(threshold));
+        // every batch is smaller than the threshold, but the whole job is not
+        assert!(parallel.parallel_for(MATCH_BATCH_SIZE * 3 / 2).is_some());
+        assert!(parallel.parallel_for(threshold).is_none());
+
+        let expected = run(job(None));
+        let result = run(parallel);
+        assert_eq!(result.candidates, expected.candidates);
+        assert_eq!(
+            result.matched[..result.ranked],
+            expected.matched[..expected.ranked]
+        );
+    }
+}